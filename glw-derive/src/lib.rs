@@ -0,0 +1,199 @@
+//! Derive macros for glw. Use them through the re-exports in ```glw::layout```.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Implements ```glw::layout::Std430``` for a ```#[repr(C)]``` struct that is also ```Pod```.
+/// Fails to compile when a field is not placed at a multiple of its std430 base alignment or when the size
+/// of the struct is not a multiple of its alignment, so the struct matches its GLSL declaration.
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    std430(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn std430(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let fields = struct_fields(input)?;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "Std430 can not be derived for generic structs"));
+    }
+
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let checks = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().expect("named fields");
+        let ty = &field.ty;
+        let message = format!("field `{}` of `{}` is not aligned to its std430 base alignment, add padding in front of it", ident, name);
+
+        quote! {
+            assert!(::core::mem::offset_of!(#name, #ident) % <#ty as ::glw::layout::Std430>::ALIGN == 0, #message);
+        }
+    });
+    let size_message = format!("the size of `{}` is not a multiple of its std430 alignment, add padding at the end", name);
+
+    Ok(quote! {
+        unsafe impl ::glw::layout::Std430 for #name {
+            const ALIGN: usize = ::glw::layout::max_align(&[#(<#types as ::glw::layout::Std430>::ALIGN),*]);
+        }
+
+        const _: () = {
+            #(#checks)*
+            assert!(::core::mem::size_of::<#name>() % <#name as ::glw::layout::Std430>::ALIGN == 0, #size_message);
+        };
+    })
+}
+
+/// Implements ```glw::layout::AsStd140```, ```AsStd430``` and ```GlslStruct``` for a struct whose fields implement them.
+/// Generates ```<Name>Std140``` and ```<Name>Std430```, copies of the struct with explicit padding in front of every
+/// field that matches the GLSL layout. The padded copies are what is uploaded to uniform and storage buffers.
+#[proc_macro_derive(GlslStruct)]
+pub fn derive_glsl_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    glsl_struct(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn glsl_struct(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let fields = struct_fields(input)?;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "GlslStruct can not be derived for generic structs"));
+    }
+
+    let std140 = padded_struct(input, fields, Layout::Std140);
+    let std430 = padded_struct(input, fields, Layout::Std430);
+
+    let declarations = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().expect("named fields").to_string();
+        let ty = &field.ty;
+        quote! {
+            definition.push_str(&format!("    {};\n", <#ty as ::glw::layout::GlslType>::glsl_declaration(#ident)));
+        }
+    });
+    let header = format!("struct {} {{\n", name);
+    let type_name = name.to_string();
+
+    Ok(quote! {
+        #std140
+        #std430
+
+        impl ::glw::layout::GlslType for #name {
+            fn glsl_declaration(name: &str) -> String {
+                format!("{} {}", #type_name, name)
+            }
+        }
+
+        impl ::glw::layout::GlslStruct for #name {
+            fn glsl_definition() -> String {
+                let mut definition = String::from(#header);
+                #(#declarations)*
+                definition.push_str("};\n");
+                definition
+            }
+        }
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Layout {
+    Std140,
+    Std430,
+}
+
+/// Generates the padded representation of the struct in ```layout``` and the conversion to it
+fn padded_struct(input: &DeriveInput, fields: &syn::punctuated::Punctuated<syn::Field, syn::Token![,]>, layout: Layout) -> TokenStream2 {
+    let name = &input.ident;
+    let vis = &input.vis;
+    // Structs are aligned to at least a vec4 in std140
+    let (padded, layout_name, layout_trait, as_trait, convert, convert_element, min_align) = match layout {
+        Layout::Std140 => (format_ident!("{}Std140", name), "std140", quote!(Std140), quote!(AsStd140), quote!(as_std140), quote!(as_std140_element), 16usize),
+        Layout::Std430 => (format_ident!("{}Std430", name), "std430", quote!(Std430), quote!(AsStd430), quote!(as_std430), quote!(as_std430_element), 1usize),
+    };
+
+    let outputs: Vec<TokenStream2> = fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            quote!(<#ty as ::glw::layout::#as_trait>::Output)
+        })
+        .collect();
+    let layouts: Vec<TokenStream2> = outputs
+        .iter()
+        .map(|output| quote!((::core::mem::size_of::<#output>(), <#output as ::glw::layout::#layout_trait>::ALIGN)))
+        .collect();
+    let align = quote!(::glw::layout::max_align(&[#(#layouts.1,)* #min_align]));
+    let padding = |index: usize| quote!(::glw::layout::padding(&[#(#layouts),*], #index, #align));
+
+    let idents: Vec<_> = fields.iter().map(|field| field.ident.as_ref().expect("named fields")).collect();
+    let pads: Vec<_> = idents.iter().map(|ident| format_ident!("_pad_{}", ident)).collect();
+    let definitions = fields.iter().zip(&outputs).enumerate().map(|(index, (field, output))| {
+        let field_vis = &field.vis;
+        let ident = &idents[index];
+        let pad = &pads[index];
+        let pad_len = padding(index);
+        quote! {
+            #pad: [u8; #pad_len],
+            #field_vis #ident: #output,
+        }
+    });
+    let end_len = padding(fields.len());
+    let pad_lens: Vec<_> = (0..=fields.len()).map(padding).collect();
+    let doc = format!("Padded representation of ```{}``` in {} blocks, generated by ```#[derive(GlslStruct)]```", name, layout_name);
+    let size_message = format!("`{}` has padding the layout does not account for", padded);
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        #vis struct #padded {
+            #(#definitions)*
+            _pad_end: [u8; #end_len],
+        }
+
+        // Pod requires that the compiler did not insert padding of its own
+        const _: () = {
+            let size = #(::core::mem::size_of::<#outputs>() +)* #(#pad_lens)+*;
+            assert!(::core::mem::size_of::<#padded>() == size, #size_message);
+        };
+
+        unsafe impl ::glw::layout::Zeroable for #padded {}
+        unsafe impl ::glw::layout::Pod for #padded {}
+
+        unsafe impl ::glw::layout::#layout_trait for #padded {
+            const ALIGN: usize = #align;
+        }
+
+        impl ::glw::layout::#as_trait for #name {
+            type Output = #padded;
+            type Element = #padded;
+
+            fn #convert(&self) -> #padded {
+                #padded {
+                    #(
+                        #pads: ::glw::layout::Zeroable::zeroed(),
+                        #idents: ::glw::layout::#as_trait::#convert(&self.#idents),
+                    )*
+                    _pad_end: ::glw::layout::Zeroable::zeroed(),
+                }
+            }
+
+            fn #convert_element(&self) -> #padded {
+                ::glw::layout::#as_trait::#convert(self)
+            }
+        }
+    }
+}
+
+fn struct_fields(input: &DeriveInput) -> Result<&syn::punctuated::Punctuated<syn::Field, syn::Token![,]>, Error> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(Error::new_spanned(&input.ident, "only structs with named fields can be used as GLSL structs")),
+        },
+        _ => Err(Error::new_spanned(&input.ident, "only structs can be used as GLSL structs")),
+    }
+}
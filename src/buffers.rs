@@ -1,4 +1,6 @@
 use super::*;
+use super::fence::Fence;
+use super::layout::{AsStd140, AsStd430, Pod};
 use std::os::raw::c_void;
 
 
@@ -10,6 +12,522 @@ pub trait BufferResource {
 	fn get_structure_size(self : &Self) -> usize;
 
 	fn get_buffer_size(self : &Self) -> usize;
+
+	/// Returns how the buffer is bound, buffers are bound as shader storage unless specified otherwise
+	fn get_usage(self : &Self) -> BufferUsage {
+		BufferUsage::Storage
+	}
+}
+
+/// Kind of data a ```Buffer``` holds, determines the target it is bound to when used
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferUsage {
+	Vertex,
+	Index,
+	Uniform,
+	Storage,
+	/// Arguments for indirect draws and dispatches
+	Indirect,
+	AtomicCounter,
+	/// Destination of pixel reads, e.g. glReadPixels
+	PixelPack,
+	/// Source of texture uploads
+	PixelUnpack,
+}
+
+impl BufferUsage {
+	/// Returns the buffer binding target for this usage
+	pub fn get(&self) -> GLenum {
+		match self {
+			BufferUsage::Vertex => gl::ARRAY_BUFFER,
+			BufferUsage::Index => gl::ELEMENT_ARRAY_BUFFER,
+			BufferUsage::Uniform => gl::UNIFORM_BUFFER,
+			BufferUsage::Storage => gl::SHADER_STORAGE_BUFFER,
+			BufferUsage::Indirect => gl::DRAW_INDIRECT_BUFFER,
+			BufferUsage::AtomicCounter => gl::ATOMIC_COUNTER_BUFFER,
+			BufferUsage::PixelPack => gl::PIXEL_PACK_BUFFER,
+			BufferUsage::PixelUnpack => gl::PIXEL_UNPACK_BUFFER,
+		}
+	}
+
+	/// Returns true for usages that are bound to numbered slots (```layout(binding = n)```)
+	pub fn is_indexed(&self) -> bool {
+		matches!(self, BufferUsage::Uniform | BufferUsage::Storage | BufferUsage::AtomicCounter)
+	}
+}
+
+/// Usage hints of mutable buffer storage, passed to ```glBufferData```. The driver is free to ignore them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UsageHint {
+	StreamDraw,
+	StreamRead,
+	StreamCopy,
+	StaticDraw,
+	StaticRead,
+	StaticCopy,
+	DynamicDraw,
+	DynamicRead,
+	DynamicCopy,
+}
+
+impl UsageHint {
+	pub fn get(&self) -> GLenum {
+		match self {
+			UsageHint::StreamDraw => gl::STREAM_DRAW,
+			UsageHint::StreamRead => gl::STREAM_READ,
+			UsageHint::StreamCopy => gl::STREAM_COPY,
+			UsageHint::StaticDraw => gl::STATIC_DRAW,
+			UsageHint::StaticRead => gl::STATIC_READ,
+			UsageHint::StaticCopy => gl::STATIC_COPY,
+			UsageHint::DynamicDraw => gl::DYNAMIC_DRAW,
+			UsageHint::DynamicRead => gl::DYNAMIC_READ,
+			UsageHint::DynamicCopy => gl::DYNAMIC_COPY,
+		}
+	}
+}
+
+/// Flags of immutable buffer storage, passed to ```glBufferStorage```.
+/// Unlike usage hints they are enforced, operations the flags do not allow return ```Error::BufferAccess```.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StorageFlags {
+	/// Allows updating the contents with ```write```
+	pub dynamic : bool,
+	/// Allows mapping the buffer for reading
+	pub map_read : bool,
+	/// Allows mapping the buffer for writing, ```write_all``` maps the buffer when set
+	pub map_write : bool,
+	/// Allows the buffer to stay mapped while the GPU uses it, requires ```map_read``` or ```map_write```
+	pub persistent : bool,
+	/// Makes writes to persistent mappings visible without flushing, requires ```persistent```
+	pub coherent : bool,
+	/// Prefers storage in client memory
+	pub client_storage : bool,
+}
+
+impl StorageFlags {
+	pub fn get(&self) -> GLbitfield {
+		[
+			(self.dynamic, gl::DYNAMIC_STORAGE_BIT),
+			(self.map_read, gl::MAP_READ_BIT),
+			(self.map_write, gl::MAP_WRITE_BIT),
+			(self.persistent, gl::MAP_PERSISTENT_BIT),
+			(self.coherent, gl::MAP_COHERENT_BIT),
+			(self.client_storage, gl::CLIENT_STORAGE_BIT),
+		]
+		.iter()
+		.filter(|(set, _)| *set)
+		.fold(0, |flags, (_, bit)| flags | bit)
+	}
+
+	/// Rejects combinations that ```glBufferStorage``` does not accept
+	pub fn validate(&self) -> Result<(), Error> {
+		if self.persistent && !(self.map_read || self.map_write) {
+			return Err(Error::InvalidArgument("persistent storage needs to allow mapping for reading or writing".to_string()));
+		}
+		if self.coherent && !self.persistent {
+			return Err(Error::InvalidArgument("coherent storage needs to be persistent".to_string()));
+		}
+
+		Ok(())
+	}
+}
+
+/// How the storage of a ```Buffer``` was allocated
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferStorage {
+	/// Storage allocated with ```glBufferData```, allows every operation
+	Mutable(UsageHint),
+	/// Storage allocated with ```glBufferStorage```, only allows the operations enabled by its flags
+	Immutable(StorageFlags),
+}
+
+/// # Buffer
+/// A GPU buffer holding ```T```s. Every buffer object in glw is a ```Buffer```, ```usage``` describes
+/// how it is bound when passed to a command list. Data is uploaded through the ```GL_COPY_WRITE_BUFFER```
+/// target so uploads never disturb the bindings of the current vertex array.
+pub struct Buffer<T>
+		where T: Pod {
+	phantom: std::marker::PhantomData<T>,
+
+	id : GLuint,
+	usage : BufferUsage,
+	storage : BufferStorage,
+	elements : usize,
+}
+
+// An empty buffer without an OpenGL buffer object
+impl<T: Pod> Default for Buffer<T> {
+	fn default() -> Self {
+		Buffer{
+			phantom: std::marker::PhantomData,
+			id: 0,
+			usage: BufferUsage::Storage,
+			storage: BufferStorage::Mutable(UsageHint::DynamicCopy),
+			elements: 0,
+		}
+	}
+}
+
+impl<T: Pod> BufferResource for Buffer<T>
+{
+	fn get_resource(self : &Self) -> *const std::ffi::c_void {
+		self.id as *const std::ffi::c_void
+	}
+
+	fn get_structure_size(self : &Self) -> usize
+	{
+		std::mem::size_of::<T>()
+	}
+
+	fn get_buffer_size(self : &Self) -> usize
+	{
+		self.get_size()
+	}
+
+	fn get_usage(self : &Self) -> BufferUsage
+	{
+		self.usage
+	}
+}
+
+impl<T: Pod> Buffer<T> {
+
+	/// Creates a buffer of ```size``` default initialized elements
+	pub fn new(usage : BufferUsage, size : usize) -> Self
+	{
+		Buffer::from_data(usage, &vec![T::zeroed(); size])
+	}
+
+	/// Creates a buffer holding a copy of ```data```, with mutable storage and a usage hint suited for ```usage```.
+	/// Use a ```BufferBuilder``` to choose the storage.
+	pub fn from_data(usage : BufferUsage, data : &[T]) -> Self
+	{
+		BufferBuilder::new(usage).build(data).expect("Mutable buffer storage can always be created")
+	}
+
+	fn create(usage : BufferUsage, storage : BufferStorage, data : &[T]) -> Result<Self, Error>
+	{
+		let mut id = 0;
+		let buffer_size = std::mem::size_of_val(data);
+
+		if let BufferStorage::Immutable(flags) = storage {
+			flags.validate()?;
+			if buffer_size == 0 {
+				return Err(Error::InvalidArgument("immutable buffer storage can not be empty".to_string()));
+			}
+			if !gl::BufferStorage::is_loaded() {
+				return Err(Error::Unsupported("immutable buffer storage (glBufferStorage)".to_string()));
+			}
+		}
+
+		unsafe{
+			gl::GenBuffers(1, &mut id);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, id);
+			match storage {
+				BufferStorage::Mutable(hint) => gl::BufferData(gl::COPY_WRITE_BUFFER, buffer_size as GLsizeiptr, data.as_ptr() as *const c_void, hint.get()),
+				BufferStorage::Immutable(flags) => gl::BufferStorage(gl::COPY_WRITE_BUFFER, buffer_size as GLsizeiptr, data.as_ptr() as *const c_void, flags.get()),
+			}
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+		}
+
+		Ok(Buffer{
+			phantom: std::marker::PhantomData,
+			id,
+			usage,
+			storage,
+			elements: data.len(),
+		})
+	}
+
+	/// Copies ```data``` into the buffer, which needs to hold exactly as many elements as the buffer.
+	/// Panics when the length differs or the storage can not be written, use ```write_all``` to handle the error instead.
+	pub fn map_data(&mut self, data : &[T]){
+		if let Err(err) = self.write_all(data) {
+			panic!("{}", err);
+		}
+	}
+
+	/// Replaces the whole contents of the buffer with ```data```, which needs to hold exactly as many elements as the buffer.
+	/// The previous contents are invalidated so the driver does not need to wait for commands still reading them.
+	/// Immutable storage needs the ```map_write``` or ```dynamic``` flag.
+	pub fn write_all(&mut self, data : &[T]) -> Result<(), Error> {
+		if data.len() != self.elements {
+			return Err(Error::InvalidArgument(format!("{} elements can not be written to a buffer of {} elements", data.len(), self.elements)));
+		}
+		match self.storage {
+			BufferStorage::Immutable(flags) if !flags.map_write => return self.write(0, data),
+			_ => (),
+		}
+		if data.is_empty() {
+			return Ok(());
+		}
+
+		unsafe{
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+
+			let d = gl::MapBufferRange(
+				gl::COPY_WRITE_BUFFER,
+				0,
+				self.get_size() as GLsizeiptr,
+				gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT) as *mut T;
+			if d.is_null() {
+				gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+				// A failed map records the reason, e.g. running out of memory, as an OpenGL error
+				Error::check_gl()?;
+				return Err(Error::BufferAccess("the buffer could not be mapped for writing".to_string()));
+			}
+
+			std::ptr::copy_nonoverlapping(data.as_ptr(), d, data.len());
+			let intact = gl::UnmapBuffer(gl::COPY_WRITE_BUFFER);
+
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+			if intact == gl::FALSE {
+				return Err(Error::BufferAccess("the data store was corrupted while the buffer was mapped, write it again".to_string()));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Writes ```data``` to the elements starting at ```offset```, the rest of the buffer is left untouched.
+	/// Immutable storage needs the ```dynamic``` flag.
+	pub fn write(&mut self, offset : usize, data : &[T]) -> Result<(), Error> {
+		check_range(&(offset..offset.saturating_add(data.len())), self.elements)?;
+		match self.storage {
+			BufferStorage::Immutable(flags) if !flags.dynamic => return Err(Error::BufferAccess("the buffer storage does not allow updates without the dynamic flag".to_string())),
+			_ => (),
+		}
+
+		unsafe{
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+			gl::BufferSubData(
+				gl::COPY_WRITE_BUFFER,
+				(offset * std::mem::size_of::<T>()) as GLintptr,
+				std::mem::size_of_val(data) as GLsizeiptr,
+				data.as_ptr() as *const c_void);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+		}
+
+		Ok(())
+	}
+
+	/// Sets every element of the buffer to ```value```
+	pub fn clear_to(&mut self, value : T) -> Result<(), Error> {
+		let data = vec![value; self.elements];
+		self.write_all(&data)
+	}
+
+	/// Copies the elements in ```range``` of ```other``` to the elements starting at ```offset``` on the GPU,
+	/// without a round trip through the CPU
+	pub fn copy_from(&mut self, other : &Buffer<T>, range : std::ops::Range<usize>, offset : usize) -> Result<(), Error> {
+		check_range(&range, other.elements)?;
+		check_range(&(offset..offset.saturating_add(range.len())), self.elements)?;
+
+		unsafe{
+			gl::BindBuffer(gl::COPY_READ_BUFFER, other.id);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+			gl::CopyBufferSubData(
+				gl::COPY_READ_BUFFER,
+				gl::COPY_WRITE_BUFFER,
+				(range.start * std::mem::size_of::<T>()) as GLintptr,
+				(offset * std::mem::size_of::<T>()) as GLintptr,
+				(range.len() * std::mem::size_of::<T>()) as GLsizeiptr);
+			gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+		}
+
+		Ok(())
+	}
+
+	/// Resizes the buffer to ```size``` elements. Existing elements are kept, new elements are default initialized.
+	/// The buffer gets a new OpenGL buffer object with the same storage, so bindings recorded before resizing refer to the old one.
+	pub fn resize(&mut self, size : usize) -> Result<(), Error> {
+		let mut resized = Buffer::create(self.usage, self.storage, &vec![T::zeroed(); size])?;
+		let kept = size.min(self.elements);
+		resized.copy_from(self, 0..kept, 0)?;
+
+		std::mem::swap(self, &mut resized);
+		Ok(())
+	}
+
+	// Returns the internal OpenGL buffer id
+	pub fn get_id(&self) -> GLuint{
+		self.id
+	}
+
+	pub fn get_usage(&self) -> BufferUsage {
+		self.usage
+	}
+
+	pub fn get_storage(&self) -> BufferStorage {
+		self.storage
+	}
+
+	/// Returns the number of elements in the buffer
+	pub fn get_len(&self) -> usize {
+		self.elements
+	}
+
+	/// Returns the size of the buffer in bytes
+	pub fn get_size(&self) -> usize {
+		std::mem::size_of::<T>() * self.elements
+	}
+
+	/// Reads the whole buffer back from the GPU, waits for all commands writing to the buffer
+	pub fn read_data(&self) -> Vec<T> {
+		self.read_range(0..self.elements).expect("The whole buffer is always in range")
+	}
+
+	/// Reads the elements in ```range``` back from the GPU, waits for all commands writing to the buffer
+	pub fn read_range(&self, range : std::ops::Range<usize>) -> Result<Vec<T>, Error> {
+		check_range(&range, self.elements)?;
+
+		let mut data = vec![T::zeroed(); range.len()];
+		unsafe{
+			gl::BindBuffer(gl::COPY_READ_BUFFER, self.id);
+			gl::GetBufferSubData(
+				gl::COPY_READ_BUFFER,
+				(range.start * std::mem::size_of::<T>()) as GLintptr,
+				std::mem::size_of_val(data.as_slice()) as GLsizeiptr,
+				data.as_mut_ptr() as *mut c_void);
+			gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+		}
+
+		Ok(data)
+	}
+
+	/// Starts copying the elements in ```range``` into a readback buffer on the GPU without waiting for it.
+	/// Poll the returned ```Readback``` once per frame to retrieve the data when the copy completed.
+	pub fn read_async(&self, range : std::ops::Range<usize>) -> Result<Readback<T>, Error> {
+		check_range(&range, self.elements)?;
+
+		let staging : Buffer<T> = BufferBuilder::new(BufferUsage::PixelPack)
+			.with_usage_hint(UsageHint::StreamRead)
+			.build_zeroed(range.len())?;
+		unsafe{
+			gl::BindBuffer(gl::COPY_READ_BUFFER, self.id);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, staging.id);
+			gl::CopyBufferSubData(
+				gl::COPY_READ_BUFFER,
+				gl::COPY_WRITE_BUFFER,
+				(range.start * std::mem::size_of::<T>()) as GLintptr,
+				0,
+				staging.get_size() as GLsizeiptr);
+			gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+		}
+
+		Ok(Readback{
+			staging,
+			fence: Fence::insert(),
+		})
+	}
+}
+
+/// # Buffer Builder
+/// Creates buffers with a chosen kind of storage. Without further options buffers get mutable storage
+/// with a usage hint suited for their ```BufferUsage```, like ```Buffer::from_data```.
+/// ```
+/// use glw::buffers::{BufferBuilder, BufferUsage, StorageFlags};
+///
+/// # fn build() -> Result<(), glw::Error> {
+/// let particles = BufferBuilder::new(BufferUsage::Storage)
+///     .with_immutable_storage(StorageFlags { dynamic: true, ..Default::default() })
+///     .build(&[0.0f32; 1024])?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BufferBuilder {
+	usage : BufferUsage,
+	storage : BufferStorage,
+}
+
+impl BufferBuilder {
+	pub fn new(usage : BufferUsage) -> BufferBuilder {
+		let hint = match usage {
+			BufferUsage::Vertex | BufferUsage::Index => UsageHint::StaticDraw,
+			BufferUsage::PixelPack => UsageHint::StreamRead,
+			_ => UsageHint::DynamicCopy,
+		};
+
+		BufferBuilder{
+			usage,
+			storage: BufferStorage::Mutable(hint),
+		}
+	}
+
+	/// Uses mutable storage (```glBufferData```) with ```hint```
+	pub fn with_usage_hint(&mut self, hint : UsageHint) -> &mut Self {
+		self.storage = BufferStorage::Mutable(hint);
+		self
+	}
+
+	/// Uses immutable storage (```glBufferStorage```) with ```flags```, requires OpenGL 4.4
+	pub fn with_immutable_storage(&mut self, flags : StorageFlags) -> &mut Self {
+		self.storage = BufferStorage::Immutable(flags);
+		self
+	}
+
+	/// Creates a buffer holding a copy of ```data```
+	pub fn build<T: Pod>(&self, data : &[T]) -> Result<Buffer<T>, Error> {
+		Buffer::create(self.usage, self.storage, data)
+	}
+
+	/// Creates a buffer of ```len``` zeroed elements
+	pub fn build_zeroed<T: Pod>(&self, len : usize) -> Result<Buffer<T>, Error> {
+		Buffer::create(self.usage, self.storage, &vec![T::zeroed(); len])
+	}
+}
+
+/// Checks that ```range``` lies within a buffer of ```len``` elements
+fn check_range(range : &std::ops::Range<usize>, len : usize) -> Result<(), Error> {
+	match range.start <= range.end && range.end <= len {
+		true => Ok(()),
+		false => Err(Error::InvalidArgument(format!("range {:?} is out of bounds for a buffer of {} elements", range, len))),
+	}
+}
+
+/// # Readback
+/// A pending copy of buffer data to the CPU started with ```Buffer::read_async```
+pub struct Readback<T>
+		where T: Pod {
+	staging : Buffer<T>,
+	fence : Fence,
+}
+
+impl<T: Pod> Readback<T> {
+
+	/// Returns true once the copy completed and ```try_read``` will return the data
+	pub fn is_ready(&self) -> bool {
+		self.fence.is_signaled()
+	}
+
+	/// Returns the data when the copy completed, never blocks
+	pub fn try_read(&self) -> Option<Vec<T>> {
+		match self.is_ready() {
+			true => Some(self.staging.read_data()),
+			false => None,
+		}
+	}
+
+	/// Blocks until the copy completed and returns the data. Fails when waiting failed, e.g. because the context was lost.
+	pub fn wait(self) -> Result<Vec<T>, Error> {
+		if !self.fence.wait_forever() {
+			// A failed wait is recorded as an OpenGL error, report that when there is one
+			Error::check_gl()?;
+			return Err(Error::BufferAccess("waiting for the readback to complete failed".to_string()));
+		}
+
+		Ok(self.staging.read_data())
+	}
+}
+
+impl<T: Pod> Drop for Buffer<T>{
+	fn drop(&mut self){
+		unsafe{
+			gl::DeleteBuffers(1, &self.id);
+		}
+	}
 }
 
 /// # Structured Buffers
@@ -18,21 +536,27 @@ pub trait BufferResource {
 /// This structured will then be mirrored in the shader programs and accessed.
 /// Once the data is mapped the buffer does not keep a copy of the CPU data. 
 /// This is up to the user to track and resubmit whenever a change occurs.
-#[derive(Default)]
+/// ```T``` needs to be ```Pod```, derive ```Std430``` as well to check it against the layout of the shader block.
+/// Dereferences to a storage ```Buffer<T>```.
 pub struct StructuredBuffer<T>
-		where T: Default + Clone {
-	phantom: std::marker::PhantomData<T>,
+		where T: Pod {
+	buffer : Buffer<T>,
+}
 
-	id : GLuint,
-	buffer_size : usize,
-	elements : usize,
+// An empty structured buffer without an OpenGL buffer object
+impl<T: Pod> Default for StructuredBuffer<T> {
+	fn default() -> Self {
+		StructuredBuffer{
+			buffer: Buffer::default(),
+		}
+	}
 }
 
 // Implements the buffer resource trait. This allows us to pass this into the renderer api
-impl<T: Default + Clone> BufferResource for StructuredBuffer<T> 
+impl<T: Pod> BufferResource for StructuredBuffer<T> 
 {
 	fn get_resource(self : &Self) -> *const std::ffi::c_void {
-		return self.id as *const std::ffi::c_void;
+		self.buffer.get_resource()
 	}
 
 	fn get_structure_size(self : &Self)  -> usize
@@ -42,89 +566,385 @@ impl<T: Default + Clone> BufferResource for StructuredBuffer<T>
 
 	fn get_buffer_size(self : &Self)  -> usize
 	{
-		return self.buffer_size 
+		self.buffer.get_size()
 	}
 }
 
-impl<T: Default + Clone> StructuredBuffer<T> {
+impl<T: Pod> StructuredBuffer<T> {
 
 	/// Creates and allocates a new empty structured buffer for use on the GPU. 
 	/// ```size``` is the amount of elements. To Map data to the buffer use ```map_data(...)```
+	/// The buffer has mutable storage, use ```from_buffer``` with a ```BufferBuilder``` to choose the storage.
 	pub fn new(size : usize) -> Self 
 	{
-		// Creates the buffer
-		let mut id = 0;
-		let buffer_size = std::mem::size_of::<T>() * size;
+		StructuredBuffer{
+			buffer: Buffer::new(BufferUsage::Storage, size),
+		}
+	}
 
-		let mut empty_data = Vec::new();
-		empty_data.resize(size, T::default());
+	/// Creates and allocates a new structured buffer with struct of type T and the provided data
+	pub fn from(data : Vec<T>) -> Self {
+		StructuredBuffer{
+			buffer: Buffer::from_data(BufferUsage::Storage, &data),
+		}
+	}
 
-		unsafe{
-			gl::GenBuffers(1,&mut id);
+	/// Simple way to copy data from a Vec to the GPU memory. 
+	/// The input vector needs to be correctly sized or else this method will panic!
+	pub fn map_data(&mut self, data : &Vec<T>){
+		self.buffer.map_data(data);
+	}
 
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, id);
-			gl::BufferData(gl::SHADER_STORAGE_BUFFER, buffer_size as isize, empty_data.as_ptr() as *const c_void, gl::DYNAMIC_COPY);
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+	/// Wraps a storage buffer, e.g. one created with a ```BufferBuilder``` to choose its storage.
+	/// Buffers with any other usage are rejected.
+	pub fn from_buffer(buffer : Buffer<T>) -> Result<Self, Error> {
+		if buffer.get_usage() != BufferUsage::Storage {
+			return Err(Error::InvalidArgument(format!("a structured buffer can not hold a {:?} buffer", buffer.get_usage())));
 		}
 
+		Ok(StructuredBuffer{
+			buffer,
+		})
+	}
+
+	/// Creates a structured buffer holding the std430 representation of ```data```, see ```GlslStruct```
+	pub fn from_std430<U: AsStd430<Element = T>>(data : &[U]) -> Self {
+		let elements : Vec<T> = data.iter().map(|d| d.as_std430_element()).collect();
 		StructuredBuffer{
-			phantom: std::marker::PhantomData,
-			id,
-			buffer_size,
-			elements: size
+			buffer: Buffer::from_data(BufferUsage::Storage, &elements),
 		}
 	}
 
-	/// Creates and allocates a new structured buffer with struct of type T and the provided data
-	pub fn from(data : Vec<T>) -> Self {
-		// Creates the buffer
+	/// Writes the std430 representation of ```data``` to the elements starting at ```offset```
+	pub fn write_std430<U: AsStd430<Element = T>>(&mut self, offset : usize, data : &[U]) -> Result<(), Error> {
+		let elements : Vec<T> = data.iter().map(|d| d.as_std430_element()).collect();
+		self.buffer.write(offset, &elements)
+	}
+}
+
+impl<T: Pod> std::ops::Deref for StructuredBuffer<T> {
+	type Target = Buffer<T>;
+
+	fn deref(&self) -> &Buffer<T> {
+		&self.buffer
+	}
+}
+
+impl<T: Pod> std::ops::DerefMut for StructuredBuffer<T> {
+	fn deref_mut(&mut self) -> &mut Buffer<T> {
+		&mut self.buffer
+	}
+}
+
+/// # Uniform Buffers
+/// Holds a single ```T``` in its std140 representation, to back a uniform block.
+/// ```T``` is usually a struct with ```#[derive(GlslStruct)]```, bind the buffer with ```bind_buffer```.
+pub struct UniformBuffer<T>
+		where T: AsStd140 {
+	buffer : Buffer<T::Output>,
+}
+
+impl<T: AsStd140> BufferResource for UniformBuffer<T>
+{
+	fn get_resource(self : &Self) -> *const std::ffi::c_void {
+		self.buffer.get_resource()
+	}
+
+	fn get_structure_size(self : &Self) -> usize
+	{
+		std::mem::size_of::<T::Output>()
+	}
+
+	fn get_buffer_size(self : &Self) -> usize
+	{
+		self.buffer.get_size()
+	}
+
+	fn get_usage(self : &Self) -> BufferUsage
+	{
+		BufferUsage::Uniform
+	}
+}
+
+impl<T: AsStd140> UniformBuffer<T> {
+
+	/// Creates a uniform buffer holding ```value```
+	pub fn new(value : &T) -> Self {
+		UniformBuffer{
+			buffer: Buffer::from_data(BufferUsage::Uniform, &[value.as_std140()]),
+		}
+	}
+
+	/// Replaces the value held by the buffer
+	pub fn write(&mut self, value : &T) {
+		self.buffer.write_all(&[value.as_std140()]).expect("A uniform buffer always holds one element");
+	}
+
+	/// Returns the buffer holding the std140 representation
+	pub fn get_buffer(&self) -> &Buffer<T::Output> {
+		&self.buffer
+	}
+}
+
+/// A range of bytes in a buffer, as handed out by ```StreamBuffer::push``` and bound with ```bind_buffer_range```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BufferRange {
+	pub offset : usize,
+	pub size : usize,
+}
+
+/// Sub-allocates a ring of equally sized regions, one region per frame in flight
+#[derive(Debug)]
+struct Ring {
+	region_size : usize,
+	regions : usize,
+	region : usize,
+	// Bytes used in the current region
+	cursor : usize,
+	alignment : usize,
+}
+
+impl Ring {
+	/// Offsets are multiples of both ```offset_alignment``` and ```element_size```
+	fn new(size : usize, regions : usize, offset_alignment : usize, element_size : usize) -> Ring {
+		let alignment = lcm(offset_alignment.max(1), element_size.max(1));
+		Ring{
+			// Every region starts aligned as well
+			region_size: size.next_multiple_of(alignment),
+			regions,
+			region: 0,
+			cursor: 0,
+			alignment,
+		}
+	}
+
+	/// Returns the offset of ```size``` bytes in the current region, None when the region is full
+	fn allocate(&mut self, size : usize) -> Option<usize> {
+		let start = self.cursor.next_multiple_of(self.alignment);
+		if start + size > self.region_size {
+			return None;
+		}
+		self.cursor = start + size;
+
+		Some(self.region * self.region_size + start)
+	}
+
+	/// Moves on to the next region and returns its index
+	fn advance(&mut self) -> usize {
+		self.region = (self.region + 1) % self.regions;
+		self.cursor = 0;
+
+		self.region
+	}
+
+	fn get_size(&self) -> usize {
+		self.region_size * self.regions
+	}
+}
+
+/// # Stream Buffers
+/// A persistently mapped buffer for data that changes every frame. The buffer is split into one region per
+/// frame in flight, data pushed during a frame is written straight into the region of that frame without stalling.
+/// ```next_frame``` fences the region and moves on to the next one, waiting only when the GPU is still
+/// reading the region from ```frames``` frames ago.
+/// ```
+/// # fn frame(stream : &mut glw::buffers::StreamBuffer<f32>, list : &mut impl glw::program::CommandList) -> Result<(), glw::Error> {
+/// let range = stream.push(&[0.5, 1.0, 2.0, 4.0])?;
+/// list.bind_buffer_range(stream, 0, range)?;
+/// stream.next_frame();
+/// # Ok(())
+/// # }
+/// ```
+pub struct StreamBuffer<T>
+		where T: Pod {
+	phantom: std::marker::PhantomData<T>,
+
+	id : GLuint,
+	usage : BufferUsage,
+	mapping : *mut u8,
+	ring : Ring,
+	// Signaled once the GPU finished the frame that used the region
+	fences : Vec<Option<Fence>>,
+}
+
+impl<T: Pod> BufferResource for StreamBuffer<T>
+{
+	fn get_resource(self : &Self) -> *const std::ffi::c_void {
+		self.id as *const std::ffi::c_void
+	}
+
+	fn get_structure_size(self : &Self) -> usize
+	{
+		std::mem::size_of::<T>()
+	}
+
+	fn get_buffer_size(self : &Self) -> usize
+	{
+		self.ring.get_size()
+	}
+
+	fn get_usage(self : &Self) -> BufferUsage
+	{
+		self.usage
+	}
+}
+
+impl<T: Pod> StreamBuffer<T> {
+
+	/// Creates a stream buffer holding up to ```elements``` elements per frame for ```frames``` frames in flight.
+	/// Requires ```glBufferStorage``` (OpenGL 4.4).
+	pub fn new(usage : BufferUsage, elements : usize, frames : usize) -> Result<Self, Error> {
+		if elements == 0 || frames == 0 {
+			return Err(Error::InvalidArgument("a stream buffer needs room for at least one element and frame".to_string()));
+		}
+		if !gl::BufferStorage::is_loaded() {
+			return Err(Error::Unsupported("persistently mapped buffers (glBufferStorage)".to_string()));
+		}
+
+		// Ranges bound to a slot need to start at a multiple of the offset alignment of the target
+		let alignment = match usage {
+			BufferUsage::Uniform => get_integer(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT),
+			BufferUsage::Storage => get_integer(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT),
+			_ => 1,
+		};
+		let ring = Ring::new(elements * std::mem::size_of::<T>(), frames, alignment, std::mem::size_of::<T>());
+
+		let flags = StorageFlags{ map_write: true, persistent: true, coherent: true, ..Default::default() }.get();
 		let mut id = 0;
-		let buffer_size = std::mem::size_of::<T>() * data.len();
+		let mapping = unsafe{
+			gl::GenBuffers(1, &mut id);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, id);
+			gl::BufferStorage(gl::COPY_WRITE_BUFFER, ring.get_size() as GLsizeiptr, std::ptr::null(), flags);
+			let mapping = gl::MapBufferRange(gl::COPY_WRITE_BUFFER, 0, ring.get_size() as GLsizeiptr, flags) as *mut u8;
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
 
-		unsafe{
-			gl::GenBuffers(1,&mut id);
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, id);
-			gl::BufferData(gl::SHADER_STORAGE_BUFFER, buffer_size as isize, data.as_ptr() as *const c_void, gl::DYNAMIC_COPY);
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+			mapping
+		};
+
+		if mapping.is_null() {
+			unsafe{
+				gl::DeleteBuffers(1, &id);
+			}
+			return Err(Error::Unsupported("persistently mapping a buffer of this size".to_string()));
 		}
 
-		StructuredBuffer{
+		Ok(StreamBuffer{
 			phantom: std::marker::PhantomData,
 			id,
-			buffer_size,
-			elements: data.len()
-		}
+			usage,
+			mapping,
+			ring,
+			fences: (0..frames).map(|_| None).collect(),
+		})
 	}
 
-	/// Simple way to copy data from a Vec to the GPU memory. 
-	/// The input vector needs to be correctly sized or else this method will panic!
-	pub fn map_data(&mut self, data : &Vec<T>){
+	/// Copies ```data``` into the region of the current frame and returns where it was written
+	pub fn push(&mut self, data : &[T]) -> Result<BufferRange, Error> {
+		let size = std::mem::size_of_val(data);
+		let offset = self.ring.allocate(size).ok_or_else(|| Error::InvalidArgument(format!(
+			"{} bytes do not fit in the remaining space of the {} byte frame region", size, self.ring.region_size)))?;
+
 		unsafe{
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.id);
+			// The mapping is coherent, so the write is visible to the GPU without flushing
+			std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, self.mapping.add(offset), size);
+		}
 
-			let d : *mut T = gl::MapBuffer(gl::SHADER_STORAGE_BUFFER, gl::WRITE_ONLY) as *mut T;
-			std::ptr::copy(data.as_ptr() as *const T, d, self.elements);
-			gl::UnmapBuffer(gl::SHADER_STORAGE_BUFFER);
+		Ok(BufferRange{ offset, size })
+	}
 
-			gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
-		}
+	/// Ends the current frame and starts writing to the next region.
+	/// Blocks when the GPU did not finish the frame that last used that region yet.
+	pub fn next_frame(&mut self) {
+		self.fences[self.ring.region] = Some(Fence::insert());
 
+		let region = self.ring.advance();
+		if let Some(fence) = self.fences[region].take() {
+			fence.wait_forever();
+		}
 	}
 
 	// Returns the internal OpenGL buffer id
-	pub fn get_id(&self) -> GLuint{
+	pub fn get_id(&self) -> GLuint {
 		self.id
 	}
 
-	pub fn get_size(&self) -> usize {
-		self.buffer_size
+	pub fn get_frames_in_flight(&self) -> usize {
+		self.ring.regions
 	}
 }
 
-impl<T: Default + Clone> Drop for StructuredBuffer<T>{
+impl<T: Pod> Drop for StreamBuffer<T>{
 	fn drop(&mut self){
 		unsafe{
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+			gl::UnmapBuffer(gl::COPY_WRITE_BUFFER);
+			gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
 			gl::DeleteBuffers(1, &self.id);
 		}
 	}
-}
\ No newline at end of file
+}
+
+fn lcm(a : usize, b : usize) -> usize {
+	let (mut x, mut y) = (a, b);
+	while y != 0 {
+		(x, y) = (y, x % y);
+	}
+
+	a / x * b
+}
+
+fn get_integer(name : GLenum) -> usize {
+	let mut value : GLint = 0;
+	unsafe{
+		gl::GetIntegerv(name, &mut value);
+	}
+
+	value.max(1) as usize
+}
+
+#[cfg(test)]
+mod buffer_tests {
+	use super::*;
+
+	#[test]
+	fn ranges_are_checked_against_the_length() {
+		assert!(check_range(&(0..4), 4).is_ok());
+		assert!(check_range(&(4..4), 4).is_ok());
+		assert!(check_range(&(2..5), 4).is_err());
+		assert!(check_range(&(3..usize::MAX), 4).is_err());
+	}
+
+	#[test]
+	fn storage_flags_map_to_bits_and_reject_invalid_combinations() {
+		let flags = StorageFlags{ map_write: true, persistent: true, coherent: true, ..Default::default() };
+		assert_eq!(flags.get(), gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT);
+		assert!(flags.validate().is_ok());
+		assert_eq!(StorageFlags::default().get(), 0);
+
+		assert!(StorageFlags{ persistent: true, ..Default::default() }.validate().is_err());
+		assert!(StorageFlags{ map_read: true, coherent: true, ..Default::default() }.validate().is_err());
+	}
+
+	#[test]
+	fn ring_regions_are_aligned_and_reused() {
+		let mut ring = Ring::new(100, 2, 64, 4);
+
+		assert_eq!(ring.get_size(), 256);
+		assert_eq!(ring.allocate(16), Some(0));
+		assert_eq!(ring.allocate(16), Some(64));
+		assert_eq!(ring.allocate(128), None);
+
+		assert_eq!(ring.advance(), 1);
+		assert_eq!(ring.allocate(128), Some(128));
+		assert_eq!(ring.advance(), 0);
+		assert_eq!(ring.allocate(16), Some(0));
+
+		// 20 byte elements with a 16 byte offset alignment start at multiples of 80
+		let mut ring = Ring::new(100, 2, 16, 20);
+		assert_eq!(ring.get_size(), 320);
+		assert_eq!(ring.allocate(20), Some(0));
+		assert_eq!(ring.allocate(20), Some(80));
+		assert_eq!(ring.advance(), 1);
+		assert_eq!(ring.allocate(20), Some(160));
+	}
+}
@@ -1,6 +1,6 @@
 use std::ops::{Add, Mul};
 /// A basic struct to hold color RGBA_UINT color data
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Color
 {
    pub r : u8,
@@ -0,0 +1,226 @@
+use super::*;
+
+/// # Command
+/// A single recorded operation of a command list. Resources are captured by their OpenGL handle,
+/// so they need to outlive every execution of the command list that references them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Binds a shader storage buffer to ```slot```
+    BindBuffer { buffer: GLuint, slot: u32 },
+    /// Binds a buffer to ```slot``` of an indexed target other than shader storage, e.g. uniform buffers
+    BindBufferBase { target: GLenum, buffer: GLuint, slot: u32 },
+    /// Binds ```size``` bytes starting at ```offset``` of a buffer to ```slot``` of an indexed target
+    BindBufferRange { target: GLenum, buffer: GLuint, slot: u32, offset: usize, size: usize },
+    BindTexture { texture: GLuint },
+    SetUniform { location: i32, uniform: Uniform },
+    SetSampler(GLuint),
+    BindPipeline(GLuint),
+    /// Binds a program pipeline object made of separable programs
+    BindProgramPipeline(GLuint),
+    /// Selects the program of a program pipeline that following ```SetUniform``` commands apply to
+    SetActiveProgram { pipeline: GLuint, program: GLuint },
+    /// Number of vertices per patch for tessellated draws
+    SetPatchVertices(u32),
+    SetViewport { x: i32, y: i32, width: i32, height: i32 },
+    Clear(Option<Color>),
+    BindRenderTarget { framebuffer: GLuint },
+    Dispatch { groups_x: u32, groups_y: u32, groups_z: u32 },
+    /// Reads the work group counts from ```buffer``` at byte ```offset```
+    DispatchIndirect { buffer: GLuint, offset: usize },
+    MemoryBarrier(MemoryBarrier),
+    Draw { vertex_array: GLuint, topology: PrimitiveTopology, first: u32, count: u32 },
+    DrawIndexed { vertex_array: GLuint, index_buffer: GLuint, topology: PrimitiveTopology, count: u32 },
+    /// Uses indexed drawing when ```index_buffer``` is not 0
+    DrawInstanced { vertex_array: GLuint, index_buffer: GLuint, topology: PrimitiveTopology, count: u32, instances: u32 },
+    /// Reads the draw arguments from ```buffer``` at byte ```offset```, uses indexed drawing when ```index_buffer``` is not 0
+    DrawIndirect { vertex_array: GLuint, index_buffer: GLuint, topology: PrimitiveTopology, buffer: GLuint, offset: usize },
+}
+
+/// State tracked while submitting a list of commands
+#[derive(Default)]
+struct ExecutionState {
+    // Program bound with glUseProgram, unknown until the list binds one
+    current_pipeline: Option<GLuint>,
+}
+
+/// Submits the recorded commands to OpenGL in order
+pub(crate) fn execute(commands: &[Command]) {
+    let mut state = ExecutionState::default();
+    for command in commands {
+        command.execute(&mut state);
+    }
+}
+
+impl Command {
+    fn execute(&self, state: &mut ExecutionState) {
+        unsafe {
+            match self {
+                Command::BindBuffer { buffer, slot } => {
+                    gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, *slot, *buffer);
+                }
+                Command::BindBufferBase { target, buffer, slot } => {
+                    gl::BindBufferBase(*target, *slot, *buffer);
+                }
+                Command::BindBufferRange { target, buffer, slot, offset, size } => {
+                    gl::BindBufferRange(*target, *slot, *buffer, *offset as GLintptr, *size as GLsizeiptr);
+                }
+                Command::BindTexture { texture } => {
+                    gl::BindImageTexture(0, *texture, 0, false as u8, 0, gl::WRITE_ONLY, gl::RGBA8);
+                }
+                Command::SetUniform { location, uniform } => {
+                    set_uniform(*location, uniform);
+                }
+                Command::SetSampler(sampler) => {
+                    bind_sampler(*sampler, gl::TEXTURE_2D);
+                }
+                Command::BindPipeline(program) => {
+                    // Rebinding the bound program is redundant
+                    if state.current_pipeline != Some(*program) {
+                        gl::UseProgram(*program);
+                        state.current_pipeline = Some(*program);
+                    }
+                }
+                Command::BindProgramPipeline(pipeline) => {
+                    // A program bound with glUseProgram takes precedence over the pipeline
+                    gl::UseProgram(0);
+                    gl::BindProgramPipeline(*pipeline);
+
+                    state.current_pipeline = Some(0);
+                }
+                Command::SetActiveProgram { pipeline, program } => {
+                    gl::ActiveShaderProgram(*pipeline, *program);
+                }
+                Command::SetPatchVertices(count) => {
+                    gl::PatchParameteri(gl::PATCH_VERTICES, *count as GLint);
+                }
+                Command::SetViewport { x, y, width, height } => {
+                    gl::Viewport(*x, *y, *width, *height);
+                }
+                Command::Clear(color) => {
+                    if let Some(c) = color {
+                        gl::ClearColor(
+                            c.r as f32 / 255.0,
+                            c.g as f32 / 255.0,
+                            c.b as f32 / 255.0,
+                            c.a as f32 / 255.0,
+                        );
+                    }
+
+                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                }
+                Command::BindRenderTarget { framebuffer } => {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, *framebuffer);
+                }
+                Command::Dispatch { groups_x, groups_y, groups_z } => {
+                    gl::DispatchCompute(*groups_x, *groups_y, *groups_z);
+                }
+                Command::DispatchIndirect { buffer, offset } => {
+                    gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, *buffer);
+                    gl::DispatchComputeIndirect(*offset as GLintptr);
+                    gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, 0);
+                }
+                Command::MemoryBarrier(barrier) => {
+                    gl::MemoryBarrier(barrier.get());
+                }
+                Command::Draw { vertex_array, topology, first, count } => {
+                    gl::BindVertexArray(*vertex_array);
+                    gl::DrawArrays(topology.get(), *first as GLint, *count as GLsizei);
+                }
+                Command::DrawIndexed { vertex_array, index_buffer, topology, count } => {
+                    gl::BindVertexArray(*vertex_array);
+                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, *index_buffer);
+                    gl::DrawElements(topology.get(), *count as GLsizei, gl::UNSIGNED_INT, std::ptr::null());
+                }
+                Command::DrawInstanced { vertex_array, index_buffer, topology, count, instances } => {
+                    gl::BindVertexArray(*vertex_array);
+                    match index_buffer {
+                        0 => gl::DrawArraysInstanced(topology.get(), 0, *count as GLsizei, *instances as GLsizei),
+                        _ => {
+                            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, *index_buffer);
+                            gl::DrawElementsInstanced(topology.get(), *count as GLsizei, gl::UNSIGNED_INT, std::ptr::null(), *instances as GLsizei);
+                        }
+                    }
+                }
+                Command::DrawIndirect { vertex_array, index_buffer, topology, buffer, offset } => {
+                    gl::BindVertexArray(*vertex_array);
+                    gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, *buffer);
+                    match index_buffer {
+                        0 => gl::DrawArraysIndirect(topology.get(), *offset as *const c_void),
+                        _ => {
+                            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, *index_buffer);
+                            gl::DrawElementsIndirect(topology.get(), gl::UNSIGNED_INT, *offset as *const c_void);
+                        }
+                    }
+                    gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+                }
+            }
+        }
+    }
+}
+
+/// Binds our input texture to the texture unit with the same index
+unsafe fn bind_sampler(sampler: GLuint, target: GLenum) {
+    gl::ActiveTexture(gl::TEXTURE0 + sampler);
+    gl::BindTexture(target, sampler);
+}
+
+/// Uploads a uniform value to a location of the currently bound program
+unsafe fn set_uniform(loc: GLint, uniform: &Uniform) {
+    match uniform {
+        Uniform::Float(v) => gl::Uniform1f(loc, *v),
+        Uniform::Int(v) => gl::Uniform1i(loc, *v),
+        Uniform::UInt(v) => gl::Uniform1ui(loc, *v),
+        Uniform::Bool(v) => gl::Uniform1i(loc, *v as GLint),
+        Uniform::Vec2(x, y) => gl::Uniform2f(loc, *x, *y),
+        Uniform::Vec3(x, y, z) => gl::Uniform3f(loc, *x, *y, *z),
+        Uniform::Vec4(x, y, z, w) => gl::Uniform4f(loc, *x, *y, *z, *w),
+        Uniform::IVec2(x, y) => gl::Uniform2i(loc, *x, *y),
+        Uniform::IVec3(x, y, z) => gl::Uniform3i(loc, *x, *y, *z),
+        Uniform::IVec4(x, y, z, w) => gl::Uniform4i(loc, *x, *y, *z, *w),
+        Uniform::UVec2(x, y) => gl::Uniform2ui(loc, *x, *y),
+        Uniform::UVec3(x, y, z) => gl::Uniform3ui(loc, *x, *y, *z),
+        Uniform::UVec4(x, y, z, w) => gl::Uniform4ui(loc, *x, *y, *z, *w),
+        Uniform::Mat2(m, transpose) => gl::UniformMatrix2fv(loc, 1, *transpose as GLboolean, m.cols.as_ptr() as *const GLfloat),
+        Uniform::Mat3(m, transpose) => gl::UniformMatrix3fv(loc, 1, *transpose as GLboolean, m.cols.as_ptr() as *const GLfloat),
+        Uniform::Mat4(m, transpose) => gl::UniformMatrix4fv(loc, 1, *transpose as GLboolean, m.as_ptr()),
+        Uniform::FloatArray(v) => gl::Uniform1fv(loc, v.len() as GLsizei, v.as_ptr()),
+        Uniform::IntArray(v) => gl::Uniform1iv(loc, v.len() as GLsizei, v.as_ptr()),
+        Uniform::UIntArray(v) => gl::Uniform1uiv(loc, v.len() as GLsizei, v.as_ptr()),
+        Uniform::BoolArray(v) => {
+            let values: Vec<GLint> = v.iter().map(|b| *b as GLint).collect();
+            gl::Uniform1iv(loc, values.len() as GLsizei, values.as_ptr());
+        }
+        // The math vector types are repr(C), so a slice of them is tightly packed components
+        Uniform::Vec2Array(v) => gl::Uniform2fv(loc, v.len() as GLsizei, v.as_ptr() as *const GLfloat),
+        Uniform::Vec3Array(v) => gl::Uniform3fv(loc, v.len() as GLsizei, v.as_ptr() as *const GLfloat),
+        Uniform::Vec4Array(v) => gl::Uniform4fv(loc, v.len() as GLsizei, v.as_ptr() as *const GLfloat),
+        Uniform::IVec2Array(v) => gl::Uniform2iv(loc, v.len() as GLsizei, v.as_ptr() as *const GLint),
+        Uniform::IVec3Array(v) => gl::Uniform3iv(loc, v.len() as GLsizei, v.as_ptr() as *const GLint),
+        Uniform::IVec4Array(v) => gl::Uniform4iv(loc, v.len() as GLsizei, v.as_ptr() as *const GLint),
+        Uniform::UVec2Array(v) => gl::Uniform2uiv(loc, v.len() as GLsizei, v.as_ptr() as *const GLuint),
+        Uniform::UVec3Array(v) => gl::Uniform3uiv(loc, v.len() as GLsizei, v.as_ptr() as *const GLuint),
+        Uniform::UVec4Array(v) => gl::Uniform4uiv(loc, v.len() as GLsizei, v.as_ptr() as *const GLuint),
+        Uniform::Mat2Array(v, transpose) => gl::UniformMatrix2fv(loc, v.len() as GLsizei, *transpose as GLboolean, v.as_ptr() as *const GLfloat),
+        Uniform::Mat3Array(v, transpose) => gl::UniformMatrix3fv(loc, v.len() as GLsizei, *transpose as GLboolean, v.as_ptr() as *const GLfloat),
+        Uniform::Mat4Array(v, transpose) => gl::UniformMatrix4fv(loc, v.len() as GLsizei, *transpose as GLboolean, v.as_ptr() as *const GLfloat),
+        Uniform::Sampler2D(v) => {
+            bind_sampler(*v, gl::TEXTURE_2D);
+            gl::Uniform1i(loc, *v as GLint);
+        }
+        Uniform::Sampler3D(v) => {
+            bind_sampler(*v, gl::TEXTURE_3D);
+            gl::Uniform1i(loc, *v as GLint);
+        }
+        Uniform::SamplerCube(v) => {
+            bind_sampler(*v, gl::TEXTURE_CUBE_MAP);
+            gl::Uniform1i(loc, *v as GLint);
+        }
+        Uniform::Sampler2DArray(v) => {
+            bind_sampler(*v, gl::TEXTURE_2D_ARRAY);
+            gl::Uniform1i(loc, *v as GLint);
+        }
+        Uniform::Image2D(unit) | Uniform::Image3D(unit) | Uniform::ImageCube(unit) | Uniform::Image2DArray(unit) => {
+            gl::Uniform1i(loc, *unit as GLint);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use super::*;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// # Diagnostic
+/// A single message of a shader info log.
+/// For shaders loaded from a file ```file``` is an index into ```PreprocessedSource::files```,
+/// otherwise it is the source string index reported by the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: usize,
+    /// 1-based line number
+    pub line: u32,
+    /// 1-based column, only reported by some drivers
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Parses a shader info log into diagnostics. Understands the formats used by Mesa (```0:12(5): error: ...```),
+    /// NVIDIA (```0(12) : error C0000: ...```) and AMD (```ERROR: 0:12: ...```). Lines without a location are skipped.
+    pub fn parse_log(log: &str) -> Vec<Diagnostic> {
+        log.lines().filter_map(Diagnostic::parse_line).collect()
+    }
+
+    fn parse_line(line: &str) -> Option<Diagnostic> {
+        let (start, end, reported) = preprocessor::parse_log_location(line)?;
+        let file = line[start..].split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()?;
+
+        // Mesa appends the column in parentheses after the line
+        let mut rest = &line[end..];
+        let mut column = None;
+        if let Some(inner) = rest.strip_prefix('(') {
+            if let Some(close) = inner.find(')') {
+                if let Ok(value) = inner[..close].parse() {
+                    column = Some(value);
+                    rest = &inner[close + 1..];
+                }
+            }
+        }
+        let rest = rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+
+        // AMD puts the severity in front of the location, Mesa and NVIDIA after it
+        let (severity, message) = match Diagnostic::severity(&line[..start]) {
+            Some(severity) => (severity, rest),
+            None => match Diagnostic::severity(rest) {
+                // Skip the severity and, for NVIDIA, the error code
+                Some(severity) => (severity, rest.split_once(':').map(|(_, m)| m).unwrap_or(rest)),
+                None => (Severity::Info, rest),
+            },
+        };
+
+        Some(Diagnostic {
+            severity,
+            file,
+            line: reported,
+            column,
+            message: message.trim().to_string(),
+        })
+    }
+
+    fn severity(text: &str) -> Option<Severity> {
+        let text = text.trim_start().to_ascii_lowercase();
+        if text.starts_with("error") {
+            Some(Severity::Error)
+        } else if text.starts_with("warning") {
+            Some(Severity::Warning)
+        } else if text.starts_with("info") || text.starts_with("note") {
+            Some(Severity::Info)
+        } else {
+            None
+        }
+    }
+
+    /// Formats the diagnostic with the offending source line and a caret below the reported column, e.g.
+    /// ```text
+    /// error: syntax error, unexpected IDENTIFIER
+    ///   --> shaders/life.frag:12:5
+    ///    |
+    /// 12 |     oops
+    ///    |     ^
+    /// ```
+    pub fn pretty(&self, file_name: &str, source_line: Option<&str>) -> String {
+        let mut location = format!("{}:{}", file_name, self.line);
+        if let Some(column) = self.column {
+            location.push_str(&format!(":{}", column));
+        }
+
+        let mut result = format!("{}: {}\n", self.severity, self.message);
+        let gutter = " ".repeat(self.line.to_string().len());
+        result.push_str(&format!("{}--> {}\n", gutter, location));
+
+        if let Some(text) = source_line {
+            // Without a column point at the first character of the statement
+            let offset = match self.column {
+                Some(column) => text.chars().take(column.saturating_sub(1) as usize).count(),
+                None => text.chars().take_while(|c| c.is_whitespace()).count(),
+            };
+            // Keep tabs so the caret lines up with the source
+            let padding: String = text.chars().take(offset).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+            result.push_str(&format!("{} |\n", gutter));
+            result.push_str(&format!("{} | {}\n", self.line, text));
+            result.push_str(&format!("{} | {}^\n", gutter, padding));
+        }
+
+        result
+    }
+}
+
+/// Formats diagnostics of a shader compiled from memory, ```source``` is the string passed to ```load_from_memory```
+pub fn pretty_print(diagnostics: &[Diagnostic], source: &str) -> String {
+    diagnostics
+        .iter()
+        .map(|d| {
+            let line = (d.line as usize).checked_sub(1).and_then(|l| source.lines().nth(l));
+            d.pretty(&d.file.to_string(), line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod diagnostic_tests {
+    use super::*;
+
+    #[test]
+    fn parses_driver_formats() {
+        let mesa = Diagnostic::parse_log("0:3(5): error: syntax error, unexpected IDENTIFIER\n0:7(1): warning: unused variable");
+        assert_eq!(mesa, vec![
+            Diagnostic { severity: Severity::Error, file: 0, line: 3, column: Some(5), message: "syntax error, unexpected IDENTIFIER".to_string() },
+            Diagnostic { severity: Severity::Warning, file: 0, line: 7, column: Some(1), message: "unused variable".to_string() },
+        ]);
+
+        let nvidia = Diagnostic::parse_log("0(12) : error C0000: syntax error, unexpected '}'");
+        assert_eq!(nvidia, vec![
+            Diagnostic { severity: Severity::Error, file: 0, line: 12, column: None, message: "syntax error, unexpected '}'".to_string() },
+        ]);
+
+        let amd = Diagnostic::parse_log("ERROR: 1:4: 'foo' : undeclared identifier\nERROR: 1 compilation errors.  No code generated.");
+        assert_eq!(amd, vec![
+            Diagnostic { severity: Severity::Error, file: 1, line: 4, column: None, message: "'foo' : undeclared identifier".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn pretty_prints_with_caret() {
+        let source = "#version 450\nvoid main() {\n    oops\n}\n";
+        let diagnostics = Diagnostic::parse_log("0:3(5): error: syntax error");
+
+        assert_eq!(pretty_print(&diagnostics, source), "error: syntax error\n --> 0:3:5\n  |\n3 |     oops\n  |     ^\n");
+    }
+}
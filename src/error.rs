@@ -0,0 +1,143 @@
+use super::*;
+
+use std::fmt;
+
+/// Status returned by glCheckFramebufferStatus for framebuffers that are not complete
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FramebufferStatus {
+    Undefined,
+    IncompleteAttachment,
+    MissingAttachment,
+    IncompleteDrawBuffer,
+    IncompleteReadBuffer,
+    Unsupported,
+    IncompleteMultisample,
+    IncompleteLayerTargets,
+    Unknown(GLenum),
+}
+
+impl FramebufferStatus {
+    pub fn from_gl(status: GLenum) -> FramebufferStatus {
+        match status {
+            gl::FRAMEBUFFER_UNDEFINED => FramebufferStatus::Undefined,
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => FramebufferStatus::IncompleteAttachment,
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => FramebufferStatus::MissingAttachment,
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => FramebufferStatus::IncompleteDrawBuffer,
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => FramebufferStatus::IncompleteReadBuffer,
+            gl::FRAMEBUFFER_UNSUPPORTED => FramebufferStatus::Unsupported,
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => FramebufferStatus::IncompleteMultisample,
+            gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => FramebufferStatus::IncompleteLayerTargets,
+            _ => FramebufferStatus::Unknown(status),
+        }
+    }
+}
+
+/// Error codes returned by glGetError
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GlError {
+    InvalidEnum,
+    InvalidValue,
+    InvalidOperation,
+    InvalidFramebufferOperation,
+    OutOfMemory,
+    StackUnderflow,
+    StackOverflow,
+    Unknown(GLenum),
+}
+
+impl GlError {
+    pub fn from_gl(error: GLenum) -> GlError {
+        match error {
+            gl::INVALID_ENUM => GlError::InvalidEnum,
+            gl::INVALID_VALUE => GlError::InvalidValue,
+            gl::INVALID_OPERATION => GlError::InvalidOperation,
+            gl::INVALID_FRAMEBUFFER_OPERATION => GlError::InvalidFramebufferOperation,
+            gl::OUT_OF_MEMORY => GlError::OutOfMemory,
+            gl::STACK_UNDERFLOW => GlError::StackUnderflow,
+            gl::STACK_OVERFLOW => GlError::StackOverflow,
+            _ => GlError::Unknown(error),
+        }
+    }
+}
+
+/// # Error
+/// Error type returned by every fallible glw API
+#[derive(Debug)]
+pub enum Error {
+    /// A shader failed to compile, ```log``` holds the shader info log and ```diagnostics``` the messages parsed from it
+    ShaderCompile { stage: ShaderType, log: String, diagnostics: Vec<Diagnostic> },
+    /// A program failed to link, ```log``` holds the program info log
+    ProgramLink { log: String, stages: Vec<ShaderType> },
+    /// The shader stages passed to a ```PipelineBuilder``` can not form a pipeline
+    InvalidStages(program::StageError),
+    /// GLSL source could not be preprocessed, e.g. because an include was not found
+    Preprocessor(String),
+    /// A framebuffer was not complete after creation
+    IncompleteFramebuffer(FramebufferStatus),
+    /// An OpenGL context could not be created
+    ContextCreation(String),
+    Io(std::io::Error),
+    /// An argument passed to glw was rejected before reaching OpenGL
+    InvalidArgument(String),
+    /// The current context does not support a requested feature
+    Unsupported(String),
+    /// A buffer could not be read or written
+    BufferAccess(String),
+    /// OpenGL reported an error through glGetError
+    Gl(GlError),
+    /// A uniform was set that is not active in the bound pipeline, reported in strict mode
+    UnknownUniform(String),
+    /// A uniform was set with a value that does not match its GLSL type, reported in strict mode
+    UniformTypeMismatch { location: i32, expected: reflection::DataType },
+}
+
+impl Error {
+    /// Returns the oldest unreported OpenGL error, if any
+    pub fn check_gl() -> Result<(), Error> {
+        match unsafe { gl::GetError() } {
+            gl::NO_ERROR => Ok(()),
+            error => Err(Error::Gl(GlError::from_gl(error))),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ShaderCompile { stage, log, .. } => write!(f, "Failed to compile {:?} shader: {}", stage, log),
+            Error::ProgramLink { log, stages } => write!(f, "Failed to link program with stages {:?}: {}", stages, log),
+            Error::InvalidStages(err) => write!(f, "Invalid shader stages: {}", err),
+            Error::Preprocessor(msg) => write!(f, "Failed to preprocess shader: {}", msg),
+            Error::IncompleteFramebuffer(status) => write!(f, "The framebuffer was not complete: {:?}", status),
+            Error::ContextCreation(msg) => write!(f, "Failed to create an OpenGL context: {}", msg),
+            Error::Io(err) => write!(f, "IO error: {}", err),
+            Error::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            Error::Unsupported(msg) => write!(f, "Unsupported by the OpenGL context: {}", msg),
+            Error::BufferAccess(msg) => write!(f, "Failed to access the buffer: {}", msg),
+            Error::Gl(err) => write!(f, "OpenGL error: {:?}", err),
+            Error::UnknownUniform(name) => write!(f, "The bound pipeline has no active uniform \"{}\"", name),
+            Error::UniformTypeMismatch { location, expected } => write!(f, "The uniform at location {} expects a value of type {:?}", location, expected),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<std::ffi::NulError> for Error {
+    fn from(err: std::ffi::NulError) -> Error {
+        Error::InvalidArgument(format!("string contains an interior nul byte at position {}", err.nul_position()))
+    }
+}
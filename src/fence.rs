@@ -0,0 +1,45 @@
+use super::*;
+
+/// # Fence
+/// A sync object inserted into the command stream. It is signaled once the GPU finished every command
+/// submitted before it, which allows polling for GPU work without stalling on it.
+pub struct Fence {
+    sync: GLsync,
+}
+
+impl Fence {
+    /// Inserts a fence after all previously submitted commands and flushes them to the GPU
+    pub fn insert() -> Fence {
+        unsafe {
+            let sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            gl::Flush();
+
+            Fence { sync }
+        }
+    }
+
+    /// Returns true when the GPU has passed the fence, never blocks
+    pub fn is_signaled(&self) -> bool {
+        self.wait(0)
+    }
+
+    /// Waits up to ```timeout_ns``` nanoseconds for the fence. Returns true when it was signaled in time.
+    pub fn wait(&self, timeout_ns: u64) -> bool {
+        unsafe {
+            matches!(gl::ClientWaitSync(self.sync, 0, timeout_ns), gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED)
+        }
+    }
+
+    /// Blocks until the GPU has passed the fence. Returns false when waiting failed, e.g. because the context was lost.
+    pub fn wait_forever(&self) -> bool {
+        self.wait(u64::MAX)
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.sync);
+        }
+    }
+}
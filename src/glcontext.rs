@@ -6,7 +6,11 @@ pub trait RenderBackend
 }
 
 /// An openGL wrapper that is used to interface with openGL.
-pub struct GLContext;
+pub struct GLContext {
+    // Keeps the EGL context alive for contexts created with new_headless
+    #[cfg(feature = "headless")]
+    _headless: Option<headless::HeadlessSurface>,
+}
 
 
 impl RenderBackend for GLContext
@@ -29,8 +33,26 @@ impl GLContext{
     /// Creates anew OpenGL Context and links up the procedure address getter
     pub fn new(window : &mut glfw::Window) -> GLContext{
         gl::load_with(|s| window.get_proc_address(s) as *const _); 
+        spirv::load_with(|s| window.get_proc_address(s) as *const _);
+
+        GLContext{
+            #[cfg(feature = "headless")]
+            _headless: None,
+        }
+    }
+
+    /// Creates a new OpenGL context without a window through EGL (surfaceless or pbuffer backed).
+    /// The context is made current on the calling thread. Rendering is expected to go to a ```RenderTarget```,
+    /// ```size``` is only used when the driver requires a pbuffer as default framebuffer.
+    #[cfg(feature = "headless")]
+    pub fn new_headless(size : math::Vec2<u32>) -> Result<GLContext, Error> {
+        let surface = headless::HeadlessSurface::new(size)?;
+        gl::load_with(|s| surface.get_proc_address(s));
+        spirv::load_with(|s| surface.get_proc_address(s));
 
-        GLContext{}
+        Ok(GLContext{
+            _headless: Some(surface),
+        })
     }
 
     /// Enables the OpenGL debug callbacks. This is only available in debug configurations
@@ -45,13 +67,16 @@ impl GLContext{
         program::GraphicsCommandList::default()
     }
 
-    pub fn execute_command_list(&self, _ : &impl program::CommandList ) 
+    /// Submits all recorded commands of the command list in order. The list is left untouched
+    /// and can be executed again.
+    pub fn execute_command_list(&self, list : &impl program::CommandList ) 
     {
-        // Do nothing in opengl
+        command::execute(list.commands());
     }
   
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MemoryBarrier {
     AtomicCounter,
     ShaderStorage,
@@ -0,0 +1,158 @@
+use super::*;
+use super::math::Vec2;
+
+use khronos_egl as egl;
+
+/// EGL_PLATFORM_SURFACELESS_MESA, not exposed by khronos-egl
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// # Headless Surface
+/// Owns the EGL display, context and (optional) pbuffer surface backing a headless `GLContext`.
+/// The surfaceless platform and `EGL_KHR_surfaceless_context` are preferred when the driver exposes them,
+/// a pbuffer of the requested size is created otherwise.
+pub struct HeadlessSurface {
+    egl: egl::DynamicInstance<egl::EGL1_4>,
+    display: egl::Display,
+    context: egl::Context,
+    surface: Option<egl::Surface>,
+}
+
+impl HeadlessSurface {
+
+    /// Loads libEGL, creates an OpenGL 4.5 core context and makes it current on the calling thread
+    pub fn new(size: Vec2<u32>) -> Result<HeadlessSurface, Error> {
+        HeadlessSurface::create(size).map_err(|msg| Error::ContextCreation(msg.to_string()))
+    }
+
+    fn create(size: Vec2<u32>) -> Result<HeadlessSurface, &'static str> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_4>::load_required() }
+            .map_err(|_| "Failed to load libEGL. EGL 1.4 or higher is required for headless contexts.")?;
+
+        let display = HeadlessSurface::get_display(&egl)?;
+        egl.initialize(display)
+            .map_err(|_| "Failed to initialize the EGL display.")?;
+
+        let result = HeadlessSurface::create_context(&egl, display, size);
+        if result.is_err() {
+            let _ = egl.terminate(display);
+        }
+
+        let (context, surface) = result?;
+        Ok(HeadlessSurface {
+            egl,
+            display,
+            context,
+            surface,
+        })
+    }
+
+    /// Returns the address of an OpenGL function, used to load the `gl` function pointers
+    pub fn get_proc_address(&self, name: &str) -> *const c_void {
+        match self.egl.get_proc_address(name) {
+            Some(f) => f as *const c_void,
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Picks the surfaceless Mesa platform when available and falls back to the default display
+    fn get_display(egl: &egl::DynamicInstance<egl::EGL1_4>) -> Result<egl::Display, &'static str> {
+        let client_extensions = egl
+            .query_string(None, egl::EXTENSIONS)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if client_extensions.contains("EGL_MESA_platform_surfaceless") {
+            if let Some(egl15) = egl.upcast::<egl::EGL1_5>() {
+                let display = unsafe {
+                    egl15.get_platform_display(PLATFORM_SURFACELESS_MESA, egl::DEFAULT_DISPLAY, &[egl::ATTRIB_NONE])
+                };
+                if let Ok(display) = display {
+                    return Ok(display);
+                }
+            }
+        }
+
+        unsafe { egl.get_display(egl::DEFAULT_DISPLAY) }
+            .ok_or("Failed to retrieve an EGL display.")
+    }
+
+    fn create_context(egl: &egl::DynamicInstance<egl::EGL1_4>, display: egl::Display, size: Vec2<u32>) -> Result<(egl::Context, Option<egl::Surface>), &'static str> {
+        let display_extensions = egl
+            .query_string(Some(display), egl::EXTENSIONS)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let surfaceless = display_extensions.contains("EGL_KHR_surfaceless_context");
+
+        // SURFACE_TYPE defaults to WINDOW_BIT, which surfaceless platforms have no configs for
+        let surface_type = match surfaceless {
+            true => 0,
+            false => egl::PBUFFER_BIT,
+        };
+        let config_attributes = [
+            egl::RENDERABLE_TYPE, egl::OPENGL_BIT,
+            egl::SURFACE_TYPE, surface_type,
+            egl::RED_SIZE, 8,
+            egl::GREEN_SIZE, 8,
+            egl::BLUE_SIZE, 8,
+            egl::ALPHA_SIZE, 8,
+            egl::NONE,
+        ];
+
+        let config = egl
+            .choose_first_config(display, &config_attributes)
+            .ok()
+            .flatten()
+            .ok_or("No EGL config supports OpenGL rendering.")?;
+
+        egl.bind_api(egl::OPENGL_API)
+            .map_err(|_| "Failed to bind the OpenGL API on the EGL display.")?;
+
+        let context_attributes = [
+            egl::CONTEXT_MAJOR_VERSION, 4,
+            egl::CONTEXT_MINOR_VERSION, 5,
+            egl::CONTEXT_OPENGL_PROFILE_MASK, egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE,
+        ];
+        let context = egl
+            .create_context(display, config, None, &context_attributes)
+            .map_err(|_| "Failed to create an OpenGL 4.5 core context through EGL.")?;
+
+        let surface = if surfaceless {
+            None
+        } else {
+            let surface_attributes = [
+                egl::WIDTH, size.x as egl::Int,
+                egl::HEIGHT, size.y as egl::Int,
+                egl::NONE,
+            ];
+            match egl.create_pbuffer_surface(display, config, &surface_attributes) {
+                Ok(surface) => Some(surface),
+                Err(_) => {
+                    let _ = egl.destroy_context(display, context);
+                    return Err("Failed to create an EGL pbuffer surface.");
+                }
+            }
+        };
+
+        if egl.make_current(display, surface, surface, Some(context)).is_err() {
+            if let Some(surface) = surface {
+                let _ = egl.destroy_surface(display, surface);
+            }
+            let _ = egl.destroy_context(display, context);
+            return Err("Failed to make the headless EGL context current.");
+        }
+
+        Ok((context, surface))
+    }
+}
+
+impl Drop for HeadlessSurface {
+    fn drop(&mut self) {
+        let _ = self.egl.make_current(self.display, None, None, None);
+        if let Some(surface) = self.surface {
+            let _ = self.egl.destroy_surface(self.display, surface);
+        }
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
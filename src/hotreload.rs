@@ -0,0 +1,128 @@
+use super::*;
+
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// Callback invoked with the error when a watched shader fails to recompile or relink
+pub type ReloadCallback = Rc<dyn Fn(&Error)>;
+
+/// # File Watch
+/// Tracks the modification times of a set of files. Polling is cheap enough to be done once per frame.
+#[derive(Clone, Debug, Default)]
+pub struct FileWatch {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl FileWatch {
+    pub fn new<I: IntoIterator<Item = PathBuf>>(files: I) -> FileWatch {
+        FileWatch {
+            files: files.into_iter().map(|f| {
+                let modified = FileWatch::modified(&f);
+                (f, modified)
+            }).collect(),
+        }
+    }
+
+    /// Returns true when any of the files changed since the watch was created or last polled
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (file, modified) in self.files.iter_mut() {
+            let current = FileWatch::modified(file);
+            if current != *modified {
+                *modified = current;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn modified(file: &PathBuf) -> Option<SystemTime> {
+        std::fs::metadata(file).and_then(|m| m.modified()).ok()
+    }
+}
+
+/// Shaders and file watch of a pipeline built with hot reloading enabled
+pub(crate) struct HotReload {
+    shaders: Vec<Rc<Shader>>,
+    watch: FileWatch,
+    callback: Option<ReloadCallback>,
+}
+
+impl HotReload {
+    pub(crate) fn new(shaders: Vec<Rc<Shader>>, callback: Option<ReloadCallback>) -> HotReload {
+        let watch = HotReload::watch(&shaders);
+        HotReload {
+            shaders,
+            watch,
+            callback,
+        }
+    }
+
+    fn watch(shaders: &[Rc<Shader>]) -> FileWatch {
+        FileWatch::new(
+            shaders
+                .iter()
+                .filter_map(|s| s.get_source())
+                .flat_map(|source| source.files.iter().cloned()),
+        )
+    }
+
+    /// Recompiles the shaders and links a new program when a source file changed.
+    /// Returns the new program, or None when nothing changed or the reload failed.
+    pub(crate) fn poll(&mut self) -> Option<GLuint> {
+        if !self.watch.poll() {
+            return None;
+        }
+
+        match self.rebuild() {
+            Ok((program, shaders)) => {
+                self.shaders = shaders;
+                self.watch = HotReload::watch(&self.shaders);
+                Some(program)
+            }
+            Err(err) => {
+                if let Some(ref callback) = self.callback {
+                    callback(&err);
+                }
+                None
+            }
+        }
+    }
+
+    fn rebuild(&self) -> Result<(GLuint, Vec<Rc<Shader>>), Error> {
+        let mut shaders = Vec::new();
+        for shader in &self.shaders {
+            shaders.push(match shader.recompile() {
+                Some(result) => Rc::new(result?),
+                None => shader.clone(),
+            });
+        }
+
+        let program = program::link_shaders(&shaders)?;
+        Ok((program, shaders))
+    }
+}
+
+#[cfg(test)]
+mod file_watch_tests {
+    use super::*;
+
+    #[test]
+    fn detects_created_and_removed_files() {
+        let dir = std::env::temp_dir().join(format!("glw_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file_watch.glsl");
+        let _ = std::fs::remove_file(&file);
+
+        let mut watch = FileWatch::new(vec![file.clone()]);
+        assert!(!watch.poll());
+
+        std::fs::write(&file, "void main() {}\n").unwrap();
+        assert!(watch.poll());
+        assert!(!watch.poll());
+
+        std::fs::remove_file(&file).unwrap();
+        assert!(watch.poll());
+    }
+}
@@ -0,0 +1,562 @@
+//! # GPU data layout
+//! Buffers only hold plain old data: ```Pod``` types have no pointers, no padding bytes and no ```Drop```,
+//! so copying them to the GPU byte for byte is sound. ```Pod``` and ```Zeroable``` are the traits of the
+//! bytemuck crate, derive them with bytemuck (types that already implement them work as is).
+//!
+//! ```Std430``` additionally checks a struct against the std430 layout rules of shader storage blocks at compile time.
+//! ```
+//! use glw::layout::{Pod, Std430, Zeroable};
+//! use glw::{Vec3, Vec4};
+//!
+//! #[derive(Clone, Copy, Pod, Zeroable, Std430)]
+//! #[repr(C)]
+//! struct Particle {
+//!     position: Vec3<f32>,
+//!     mass: f32,
+//!     velocity: Vec4<f32>,
+//! }
+//!
+//! assert_eq!(Particle::ALIGN, 16);
+//! ```
+//! A vec3 directly followed by a vec4 is rejected, as GLSL places the vec4 at offset 16:
+//! ```compile_fail
+//! use glw::layout::{Pod, Std430, Zeroable};
+//! use glw::{Vec3, Vec4};
+//!
+//! #[derive(Clone, Copy, Pod, Zeroable, Std430)]
+//! #[repr(C)]
+//! struct Particle {
+//!     position: Vec3<f32>,
+//!     velocity: Vec4<f32>,
+//! }
+//! ```
+//!
+//! Structs that do not match the GLSL layout as they are derive ```GlslStruct``` instead. It generates padded copies
+//! of the struct for std140 (```<Name>Std140```) and std430 (```<Name>Std430```) blocks and the GLSL declaration.
+//! ```
+//! use glw::layout::GlslStruct;
+//! use glw::{Mat4, Vec3};
+//!
+//! #[derive(GlslStruct)]
+//! struct Camera {
+//!     view_projection: Mat4,
+//!     position: Vec3<f32>,
+//!     exposure: f32,
+//!     jitter: [f32; 2],
+//! }
+//!
+//! // Array elements are padded to 16 bytes in std140
+//! assert_eq!(std::mem::size_of::<CameraStd140>(), 112);
+//! assert!(Camera::glsl_definition().contains("float jitter[2];"));
+//! ```
+//! The padded copies are uploaded with ```UniformBuffer<Camera>``` or ```StructuredBuffer::from_std430```.
+
+use super::math::{Mat2, Mat3, Mat4, Vec2, Vec3, Vec4};
+
+pub use bytemuck;
+pub use bytemuck::{Pod, Zeroable};
+pub use glw_derive::{GlslStruct, Std430};
+
+// Vectors and matrices are repr(C) arrays of scalars without padding
+unsafe impl<T: Zeroable> Zeroable for Vec2<T> {}
+unsafe impl<T: Pod> Pod for Vec2<T> {}
+unsafe impl<T: Zeroable> Zeroable for Vec3<T> {}
+unsafe impl<T: Pod> Pod for Vec3<T> {}
+unsafe impl<T: Zeroable> Zeroable for Vec4<T> {}
+unsafe impl<T: Pod> Pod for Vec4<T> {}
+unsafe impl Zeroable for Mat2 {}
+unsafe impl Pod for Mat2 {}
+unsafe impl Zeroable for Mat3 {}
+unsafe impl Pod for Mat3 {}
+unsafe impl Zeroable for Mat4 {}
+unsafe impl Pod for Mat4 {}
+
+/// # Std430
+/// Types with a known base alignment in std430 blocks. Implement it with ```#[derive(Std430)]```.
+///
+/// # Safety
+/// ```ALIGN``` has to be the std430 base alignment of the GLSL equivalent of the type and the type
+/// has to have the same size as its GLSL equivalent.
+pub unsafe trait Std430: Pod {
+    /// Base alignment in bytes
+    const ALIGN: usize;
+}
+
+unsafe impl Std430 for f32 {
+    const ALIGN: usize = 4;
+}
+
+unsafe impl Std430 for i32 {
+    const ALIGN: usize = 4;
+}
+
+unsafe impl Std430 for u32 {
+    const ALIGN: usize = 4;
+}
+
+// Only vectors of scalars have a GLSL equivalent
+macro_rules! vector_std430 {
+    ($($scalar:ty),*) => {$(
+        unsafe impl Std430 for Vec2<$scalar> {
+            const ALIGN: usize = 8;
+        }
+
+        // A vec3 is aligned like a vec4 but only 12 bytes large, a following scalar fills the gap
+        unsafe impl Std430 for Vec3<$scalar> {
+            const ALIGN: usize = 16;
+        }
+
+        unsafe impl Std430 for Vec4<$scalar> {
+            const ALIGN: usize = 16;
+        }
+    )*};
+}
+
+vector_std430!(f32, i32, u32);
+
+unsafe impl Std430 for Mat2 {
+    const ALIGN: usize = 8;
+}
+
+unsafe impl Std430 for Mat4 {
+    const ALIGN: usize = 16;
+}
+
+// Arrays are aligned like their elements, the elements need to be padded to their alignment
+unsafe impl<T: Std430, const N: usize> Std430 for [T; N] {
+    const ALIGN: usize = {
+        assert!(std::mem::size_of::<T>() % T::ALIGN == 0, "the size of an array element needs to be a multiple of its std430 alignment");
+        T::ALIGN
+    };
+}
+
+/// # Std140
+/// Types with a known base alignment in std140 blocks, the layout of uniform blocks.
+///
+/// # Safety
+/// ```ALIGN``` has to be the std140 base alignment of the GLSL equivalent of the type and the type
+/// has to have the same size as its GLSL equivalent.
+pub unsafe trait Std140: Pod {
+    /// Base alignment in bytes
+    const ALIGN: usize;
+}
+
+unsafe impl Std140 for Mat4 {
+    const ALIGN: usize = 16;
+}
+
+// Arrays are aligned like a vec4 in std140, so every element needs to be padded to a multiple of 16 bytes
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = {
+        assert!(std::mem::size_of::<T>() % 16 == 0, "the size of an array element needs to be a multiple of 16 bytes in std140");
+        max_align(&[T::ALIGN, 16])
+    };
+}
+
+/// A value followed by ```N``` 32-bit words of padding, the std140 and std430 representation of array
+/// elements and matrix columns that are smaller than their stride
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Padded<T, const N: usize> {
+    pub value: T,
+    padding: [u32; N],
+}
+
+impl<T, const N: usize> Padded<T, N> {
+    pub fn new(value: T) -> Self {
+        Padded { value, padding: [0; N] }
+    }
+}
+
+/// Converts a type to the representation it has in std140 blocks, e.g. a uniform block.
+/// Implement it with ```#[derive(GlslStruct)]```.
+pub trait AsStd140 {
+    /// Representation as a block member
+    type Output: Std140;
+    /// Representation as an array element, padded to the array stride
+    type Element: Std140;
+
+    fn as_std140(&self) -> Self::Output;
+
+    fn as_std140_element(&self) -> Self::Element;
+}
+
+/// Converts a type to the representation it has in std430 blocks, e.g. a shader storage block.
+/// Implement it with ```#[derive(GlslStruct)]```.
+pub trait AsStd430 {
+    /// Representation as a block member
+    type Output: Std430;
+    /// Representation as an array element, padded to the array stride
+    type Element: Std430;
+
+    fn as_std430(&self) -> Self::Output;
+
+    fn as_std430_element(&self) -> Self::Element;
+}
+
+/// Types with a GLSL equivalent
+pub trait GlslType {
+    /// Returns the declaration of a variable of this type called ```name```, e.g. ```vec3 name``` or ```float name[4]```
+    fn glsl_declaration(name: &str) -> String;
+}
+
+/// Structs with a GLSL equivalent. Implement it with ```#[derive(GlslStruct)]```.
+pub trait GlslStruct: GlslType {
+    /// Returns the GLSL struct definition, structs used by the members are not included
+    fn glsl_definition() -> String;
+}
+
+// Types that are the same in both layouts and as array elements
+macro_rules! unpadded_layout {
+    ($($ty:ty),*) => {$(
+        impl AsStd430 for $ty {
+            type Output = $ty;
+            type Element = $ty;
+
+            fn as_std430(&self) -> $ty {
+                *self
+            }
+
+            fn as_std430_element(&self) -> $ty {
+                *self
+            }
+        }
+    )*};
+}
+
+macro_rules! scalar_layouts {
+    ($($scalar:ty => $name:expr, $prefix:expr;)*) => {$(
+        unsafe impl Zeroable for Padded<$scalar, 3> {}
+        unsafe impl Pod for Padded<$scalar, 3> {}
+        unsafe impl Zeroable for Padded<Vec2<$scalar>, 2> {}
+        unsafe impl Pod for Padded<Vec2<$scalar>, 2> {}
+        unsafe impl Zeroable for Padded<Vec3<$scalar>, 1> {}
+        unsafe impl Pod for Padded<Vec3<$scalar>, 1> {}
+
+        unsafe impl Std140 for $scalar {
+            const ALIGN: usize = 4;
+        }
+        unsafe impl Std140 for Vec2<$scalar> {
+            const ALIGN: usize = 8;
+        }
+        unsafe impl Std140 for Vec3<$scalar> {
+            const ALIGN: usize = 16;
+        }
+        unsafe impl Std140 for Vec4<$scalar> {
+            const ALIGN: usize = 16;
+        }
+        unsafe impl Std140 for Padded<$scalar, 3> {
+            const ALIGN: usize = 16;
+        }
+        unsafe impl Std140 for Padded<Vec2<$scalar>, 2> {
+            const ALIGN: usize = 16;
+        }
+        unsafe impl Std140 for Padded<Vec3<$scalar>, 1> {
+            const ALIGN: usize = 16;
+        }
+        unsafe impl Std430 for Padded<Vec3<$scalar>, 1> {
+            const ALIGN: usize = 16;
+        }
+
+        impl AsStd140 for $scalar {
+            type Output = $scalar;
+            type Element = Padded<$scalar, 3>;
+
+            fn as_std140(&self) -> $scalar {
+                *self
+            }
+
+            fn as_std140_element(&self) -> Self::Element {
+                Padded::new(*self)
+            }
+        }
+
+        impl AsStd140 for Vec2<$scalar> {
+            type Output = Vec2<$scalar>;
+            type Element = Padded<Vec2<$scalar>, 2>;
+
+            fn as_std140(&self) -> Self::Output {
+                *self
+            }
+
+            fn as_std140_element(&self) -> Self::Element {
+                Padded::new(*self)
+            }
+        }
+
+        impl AsStd140 for Vec3<$scalar> {
+            type Output = Vec3<$scalar>;
+            type Element = Padded<Vec3<$scalar>, 1>;
+
+            fn as_std140(&self) -> Self::Output {
+                *self
+            }
+
+            fn as_std140_element(&self) -> Self::Element {
+                Padded::new(*self)
+            }
+        }
+
+        impl AsStd140 for Vec4<$scalar> {
+            type Output = Vec4<$scalar>;
+            type Element = Vec4<$scalar>;
+
+            fn as_std140(&self) -> Self::Output {
+                *self
+            }
+
+            fn as_std140_element(&self) -> Self::Element {
+                *self
+            }
+        }
+
+        // A vec3 array element is padded to 16 bytes in std430 as well
+        impl AsStd430 for Vec3<$scalar> {
+            type Output = Vec3<$scalar>;
+            type Element = Padded<Vec3<$scalar>, 1>;
+
+            fn as_std430(&self) -> Self::Output {
+                *self
+            }
+
+            fn as_std430_element(&self) -> Self::Element {
+                Padded::new(*self)
+            }
+        }
+
+        unpadded_layout!($scalar, Vec2<$scalar>, Vec4<$scalar>);
+
+        impl GlslType for $scalar {
+            fn glsl_declaration(name: &str) -> String {
+                format!("{} {}", $name, name)
+            }
+        }
+
+        impl GlslType for Vec2<$scalar> {
+            fn glsl_declaration(name: &str) -> String {
+                format!("{}vec2 {}", $prefix, name)
+            }
+        }
+
+        impl GlslType for Vec3<$scalar> {
+            fn glsl_declaration(name: &str) -> String {
+                format!("{}vec3 {}", $prefix, name)
+            }
+        }
+
+        impl GlslType for Vec4<$scalar> {
+            fn glsl_declaration(name: &str) -> String {
+                format!("{}vec4 {}", $prefix, name)
+            }
+        }
+    )*};
+}
+
+scalar_layouts! {
+    f32 => "float", "";
+    i32 => "int", "i";
+    u32 => "uint", "u";
+}
+
+// Matrices are arrays of column vectors
+impl AsStd140 for Mat2 {
+    type Output = [Padded<Vec2<f32>, 2>; 2];
+    type Element = [Padded<Vec2<f32>, 2>; 2];
+
+    fn as_std140(&self) -> Self::Output {
+        std::array::from_fn(|i| Padded::new(Vec2::new(self.cols[i][0], self.cols[i][1])))
+    }
+
+    fn as_std140_element(&self) -> Self::Element {
+        self.as_std140()
+    }
+}
+
+impl AsStd140 for Mat3 {
+    type Output = [Padded<Vec3<f32>, 1>; 3];
+    type Element = [Padded<Vec3<f32>, 1>; 3];
+
+    fn as_std140(&self) -> Self::Output {
+        std::array::from_fn(|i| Padded::new(Vec3::new(self.cols[i][0], self.cols[i][1], self.cols[i][2])))
+    }
+
+    fn as_std140_element(&self) -> Self::Element {
+        self.as_std140()
+    }
+}
+
+impl AsStd430 for Mat3 {
+    type Output = [Padded<Vec3<f32>, 1>; 3];
+    type Element = [Padded<Vec3<f32>, 1>; 3];
+
+    fn as_std430(&self) -> Self::Output {
+        self.as_std140()
+    }
+
+    fn as_std430_element(&self) -> Self::Element {
+        self.as_std140()
+    }
+}
+
+impl AsStd140 for Mat4 {
+    type Output = Mat4;
+    type Element = Mat4;
+
+    fn as_std140(&self) -> Mat4 {
+        *self
+    }
+
+    fn as_std140_element(&self) -> Mat4 {
+        *self
+    }
+}
+
+unpadded_layout!(Mat2, Mat4);
+
+impl GlslType for Mat2 {
+    fn glsl_declaration(name: &str) -> String {
+        format!("mat2 {}", name)
+    }
+}
+
+impl GlslType for Mat3 {
+    fn glsl_declaration(name: &str) -> String {
+        format!("mat3 {}", name)
+    }
+}
+
+impl GlslType for Mat4 {
+    fn glsl_declaration(name: &str) -> String {
+        format!("mat4 {}", name)
+    }
+}
+
+impl<T: AsStd140, const N: usize> AsStd140 for [T; N] {
+    type Output = [T::Element; N];
+    type Element = [T::Element; N];
+
+    fn as_std140(&self) -> Self::Output {
+        std::array::from_fn(|i| self[i].as_std140_element())
+    }
+
+    fn as_std140_element(&self) -> Self::Element {
+        self.as_std140()
+    }
+}
+
+impl<T: AsStd430, const N: usize> AsStd430 for [T; N] {
+    type Output = [T::Element; N];
+    type Element = [T::Element; N];
+
+    fn as_std430(&self) -> Self::Output {
+        std::array::from_fn(|i| self[i].as_std430_element())
+    }
+
+    fn as_std430_element(&self) -> Self::Element {
+        self.as_std430()
+    }
+}
+
+impl<T: GlslType, const N: usize> GlslType for [T; N] {
+    fn glsl_declaration(name: &str) -> String {
+        T::glsl_declaration(&format!("{}[{}]", name, N))
+    }
+}
+
+/// Returns the padding in front of the field at ```index``` of a struct with fields of the given
+/// sizes and alignments, or the padding at the end when ```index``` is the number of fields.
+/// Used by ```#[derive(GlslStruct)]```.
+#[doc(hidden)]
+pub const fn padding(fields: &[(usize, usize)], index: usize, struct_align: usize) -> usize {
+    let mut offset: usize = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        let aligned = offset.next_multiple_of(fields[i].1);
+        if i == index {
+            return aligned - offset;
+        }
+        offset = aligned + fields[i].0;
+        i += 1;
+    }
+
+    offset.next_multiple_of(struct_align) - offset
+}
+
+/// Returns the largest alignment, used by the derive macros
+#[doc(hidden)]
+pub const fn max_align(aligns: &[usize]) -> usize {
+    let mut max = 1;
+    let mut i = 0;
+    while i < aligns.len() {
+        if aligns[i] > max {
+            max = aligns[i];
+        }
+        i += 1;
+    }
+
+    max
+}
+
+#[cfg(test)]
+mod layout_tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Pod, Zeroable, Std430)]
+    #[repr(C)]
+    struct Light {
+        direction: Vec3<f32>,
+        intensity: f32,
+        shadow: Mat4,
+        cascades: [Vec2<f32>; 3],
+        count: u32,
+        padding: u32,
+    }
+
+    #[test]
+    fn derived_alignment_is_the_largest_member_alignment() {
+        assert_eq!(Light::ALIGN, 16);
+        assert_eq!(<[Vec2<u32>; 4] as Std430>::ALIGN, 8);
+        assert_eq!(std::mem::size_of::<Light>(), 112);
+    }
+
+    #[derive(GlslStruct)]
+    pub(crate) struct Material {
+        pub(crate) albedo: Vec3<f32>,
+        roughness: f32,
+        emissive: Vec3<f32>,
+        weights: [f32; 3],
+        transform: Mat3,
+    }
+
+    #[test]
+    fn padded_representations_follow_the_block_layouts() {
+        use std::mem::{offset_of, size_of};
+
+        assert_eq!((offset_of!(MaterialStd140, emissive), offset_of!(MaterialStd140, weights), offset_of!(MaterialStd140, transform)), (16, 32, 80));
+        assert_eq!(size_of::<MaterialStd140>(), 128);
+
+        assert_eq!((offset_of!(MaterialStd430, emissive), offset_of!(MaterialStd430, weights), offset_of!(MaterialStd430, transform)), (16, 28, 48));
+        assert_eq!(size_of::<MaterialStd430>(), 96);
+
+        let material = Material {
+            albedo: Vec3::new(1.0, 0.5, 0.25),
+            roughness: 0.75,
+            emissive: Vec3::new(0.0, 0.0, 0.0),
+            weights: [1.0, 2.0, 3.0],
+            transform: Mat3::identity(),
+        };
+        let std140 = material.as_std140();
+        assert_eq!((std140.albedo, std140.roughness), (material.albedo, 0.75));
+        assert_eq!(std140.weights[2].value, 3.0);
+        assert_eq!(std140.transform[1].value, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn emits_the_glsl_definition() {
+        assert_eq!(
+            Material::glsl_definition(),
+            "struct Material {\n    vec3 albedo;\n    float roughness;\n    vec3 emissive;\n    float weights[3];\n    mat3 transform;\n};\n"
+        );
+        assert_eq!(<[[Vec4<u32>; 2]; 3]>::glsl_declaration("grid"), "uvec4 grid[3][2]");
+    }
+}
@@ -3,6 +3,9 @@
 pub use gl as gl;
 pub use glfw as glfw;
 
+// Lets the derive macros refer to ```::glw``` inside of this crate
+extern crate self as glw;
+
 pub mod shader;
 pub mod program;
 pub mod color;
@@ -11,14 +14,41 @@ pub mod rendertarget;
 pub mod mesh;
 pub mod glcontext;
 pub mod buffers;
+pub mod command;
+pub mod error;
+pub mod reflection;
+pub mod preprocessor;
+pub mod hotreload;
+pub mod diagnostics;
+pub mod spirv;
+pub mod programcache;
+pub mod variants;
+pub mod separable;
+pub mod fence;
+pub mod layout;
+#[cfg(feature = "headless")]
+pub mod headless;
+#[cfg(feature = "validation")]
+pub mod validation;
 
-pub use self::mesh::{Mesh,MeshBuilder};
-pub use self::program::{GraphicsPipeline,PipelineBuilder};
-pub use self::math::Vec2;
+pub use self::mesh::{Mesh,MeshBuilder,GeometryResource,PrimitiveTopology};
+pub use self::program::{GraphicsPipeline,ComputePipeline,PipelineBuilder};
+pub use self::math::{Vec2, Vec3, Vec4, Mat2, Mat3, Mat4};
 pub use self::color::Color;
-pub use self::shader::{Shader, Uniform};
+pub use self::shader::{Shader, ShaderType, Uniform};
 pub use self::rendertarget::{RenderTarget};
 pub use self::glcontext::{GLContext, MemoryBarrier};
+pub use self::command::Command;
+pub use self::error::Error;
+pub use self::reflection::{ProgramReflection, UniformCache, UniformHandle};
+pub use self::preprocessor::{Preprocessor, PreprocessedSource};
+pub use self::hotreload::FileWatch;
+pub use self::diagnostics::{Diagnostic, Severity};
+pub use self::spirv::Specialization;
+pub use self::programcache::{ProgramCache, CacheStats};
+pub use self::variants::{ShaderVariantSet, VariantKey};
+pub use self::separable::{SeparableProgram, ProgramPipeline};
+pub use self::fence::Fence;
 
 use gl::types::*;
 use std::os::raw::c_void;
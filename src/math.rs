@@ -6,7 +6,8 @@ pub trait DotProduct<RHS=Self>{
 	#[must_use]
 	fn dot(&self, rhs : RHS ) -> Self::Output;
 }
-#[derive(Default,Clone, PartialEq, Debug)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+#[repr(C)]
 pub struct Vec2<T>{
     pub x : T,
     pub y : T,
@@ -61,7 +62,8 @@ impl<T> Div for Vec2<T>
 }
 
 
-#[derive(Default, PartialEq, Clone, Debug)]
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+#[repr(C)]
 pub struct Vec3<T>{
 	pub x : T,
 	pub y : T,
@@ -87,6 +89,85 @@ impl<T> DotProduct for Vec3<T>
 	}
 }
 
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Vec4<T>{
+	pub x : T,
+	pub y : T,
+	pub z : T,
+	pub w : T,
+}
+
+impl<T>  Vec4<T> {
+	#[allow(dead_code)]
+	pub fn new(x: T, y: T, z: T, w: T) -> Self{
+		Vec4{
+			x,
+			y,
+			z,
+			w,
+		}
+	}
+}
+impl<T> DotProduct for Vec4<T>
+	where T: Mul<Output=T> + Add<Output=T> + Copy
+{
+	type Output = T;
+	fn dot(&self, rhs : Self) -> T{
+		self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+	}
+}
+
+/// Column major 2x2 float matrix, laid out the way GLSL expects a mat2
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Mat2{
+	pub cols : [[f32; 2]; 2],
+}
+
+/// Column major 3x3 float matrix, laid out the way GLSL expects a mat3
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Mat3{
+	pub cols : [[f32; 3]; 3],
+}
+
+/// Column major 4x4 float matrix, laid out the way GLSL expects a mat4
+#[derive(Default, PartialEq, Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Mat4{
+	pub cols : [[f32; 4]; 4],
+}
+
+impl Mat2 {
+	pub fn identity() -> Self{
+		Mat2{
+			cols: [[1.0, 0.0], [0.0, 1.0]],
+		}
+	}
+}
+
+impl Mat3 {
+	pub fn identity() -> Self{
+		Mat3{
+			cols: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+		}
+	}
+}
+
+impl Mat4 {
+	pub fn identity() -> Self{
+		Mat4{
+			cols: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+		}
+	}
+
+	/// Returns the column major element data
+	pub fn as_ptr(&self) -> *const f32{
+		self.cols.as_ptr() as *const f32
+	}
+}
+
 #[cfg(test)]
 mod vec2_tests
 {
@@ -152,4 +233,35 @@ mod vec3_tests
 		let dot_product = a.dot(b);
 		assert_eq!(dot_product,4.0);
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod vec4_tests
+{
+	use super::*;
+
+	#[test]
+	fn dot_product_same(){
+		let a = Vec4::new(1.0,2.0,0.0,1.0);
+		let b = Vec4::new(1.0,2.0,0.0,1.0);
+
+		assert_eq!(a.dot(b),6.0);
+	}
+}
+
+#[cfg(test)]
+mod mat_tests
+{
+	use super::*;
+
+	#[test]
+	fn identity_is_column_major(){
+		let m = Mat4::identity();
+		let data = unsafe { std::slice::from_raw_parts(m.as_ptr(), 16) };
+
+		assert_eq!(data[0], 1.0);
+		assert_eq!(data[5], 1.0);
+		assert_eq!(data[4], 0.0);
+		assert_eq!(std::mem::size_of::<Mat3>(), 9 * 4);
+	}
+}
@@ -1,22 +1,99 @@
 use super::*;
+use super::buffers::{Buffer, BufferUsage};
+
+/// Primitive topologies used to interpret vertex data of draw commands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrimitiveTopology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    /// Patches for pipelines with tessellation stages, the vertex count is set on the pipeline
+    Patches,
+}
+
+impl PrimitiveTopology {
+    pub fn get(&self) -> GLenum {
+        match self {
+            PrimitiveTopology::Points => gl::POINTS,
+            PrimitiveTopology::Lines => gl::LINES,
+            PrimitiveTopology::LineStrip => gl::LINE_STRIP,
+            PrimitiveTopology::LineLoop => gl::LINE_LOOP,
+            PrimitiveTopology::Triangles => gl::TRIANGLES,
+            PrimitiveTopology::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveTopology::TriangleFan => gl::TRIANGLE_FAN,
+            PrimitiveTopology::Patches => gl::PATCHES,
+        }
+    }
+}
+
+/// # Geometry Resource interface
+/// Used to pass vertex input state into the draw commands of the renderer API.
+/// Implement this for raw vertex/index buffers that are not created through ```MeshBuilder```.
+pub trait GeometryResource {
+    /// Returns the vertex array object describing the vertex layout
+    fn get_vertex_array(self: &Self) -> GLuint;
+
+    /// Returns the index buffer holding ```u32``` indices, 0 when the geometry is not indexed
+    fn get_index_buffer(self: &Self) -> GLuint;
+
+    fn get_vertex_count(self: &Self) -> u32;
+
+    fn get_index_count(self: &Self) -> u32;
+}
 
 pub struct Mesh {
-    _vertex_count: i32,
+    vertex_count: i32,
     index_count: i32,
-    ibo: GLuint,
+    vbo: Option<Buffer<f32>>,
+    ibo: Option<Buffer<i32>>,
     vao: GLuint,
 }
 
+impl GeometryResource for Mesh {
+    fn get_vertex_array(self: &Self) -> GLuint {
+        self.vao
+    }
+
+    fn get_index_buffer(self: &Self) -> GLuint {
+        self.ibo.as_ref().map(|b| b.get_id()).unwrap_or(0)
+    }
+
+    fn get_vertex_count(self: &Self) -> u32 {
+        self.vertex_count as u32
+    }
+
+    fn get_index_count(self: &Self) -> u32 {
+        self.index_count as u32
+    }
+}
+
 impl Mesh {
+    /// Returns the buffer holding the interleaved vertex data
+    pub fn get_vertex_buffer(&self) -> Option<&Buffer<f32>> {
+        self.vbo.as_ref()
+    }
+
     pub fn draw(&self) {
         unsafe{
             gl::BindVertexArray(self.vao);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER,self.ibo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER,self.get_index_buffer());
             gl::DrawElements(gl::TRIANGLES, self.index_count, gl::UNSIGNED_INT, std::ptr::null());
         } 
     }
 }
 
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct MeshBuilder {
     indices: Option<Vec<i32>>,
@@ -44,46 +121,23 @@ impl MeshBuilder {
     // #TODO: Build the mesh using a Vertex Description struct
     pub fn build(&self) -> Mesh {
         // Create indices
-        let (mut ibo, mut vao) = (0, 0);
+        let mut vao = 0;
         let (mut vert_count, mut ind_count) = (0,0);
-        if let Some(ref data) = self.indices {
-            vert_count = data.len();
-            unsafe {
-                gl::GenBuffers(1, &mut ibo);
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
-                gl::BufferData(
-                    gl::ELEMENT_ARRAY_BUFFER,
-                    (data.len() * std::mem::size_of::<GLint>()) as GLsizeiptr,
-                    &data[0] as *const GLint as *const std::os::raw::c_void,
-                    gl::STATIC_DRAW,
-                );
-                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            }
-        }
+        let ibo = self.indices.as_ref().map(|data| {
+            ind_count = data.len();
+            Buffer::from_data(BufferUsage::Index, data)
+        });
 
         // Create VAO
-        if let Some(ref data) = self.vertices {
-            ind_count = data.len();
+        let vbo = self.vertices.as_ref().map(|data| Buffer::from_data(BufferUsage::Vertex, data));
+        if let Some(ref vbo) = vbo {
+            // Vertices are interleaved position (3), uv (2) and normal (3)
+            vert_count = vbo.get_len() / 8;
 
-            vao = 0;
-            let mut vbo = 0;
             unsafe {
                 gl::GenVertexArrays(1, &mut vao);
                 gl::BindVertexArray(vao);
-
-                gl::GenBuffers(1, &mut vbo);
-
-
-                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-                gl::BufferData(
-                    gl::ARRAY_BUFFER,
-                    (data.len() * std::mem::size_of::<GLfloat>()) as GLsizeiptr,
-                    &data[0] as *const f32 as *const std::os::raw::c_void,
-                    gl::STATIC_DRAW,
-                );
-
-                gl::BindVertexArray(vao);
-                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo.get_id());
 
                 let stride = 8 * std::mem::size_of::<GLfloat>() as GLsizei;
                 gl::EnableVertexAttribArray(0);
@@ -117,8 +171,9 @@ impl MeshBuilder {
         }
 
         Mesh{
-            _vertex_count: vert_count as i32,
+            vertex_count: vert_count as i32,
             index_count: ind_count as i32,
+            vbo,
             ibo,
             vao,
         }
@@ -0,0 +1,369 @@
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+/// Location of a line of preprocessed source in the original files
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLocation {
+    /// Index into ```PreprocessedSource::files```
+    pub file: usize,
+    /// 1-based line number in that file
+    pub line: u32,
+}
+
+/// # Preprocessed Source
+/// GLSL source with all includes resolved, together with the information needed to map
+/// lines reported by the compiler back to the files they came from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PreprocessedSource {
+    pub source: String,
+    /// Every file that contributed to the source, the root file comes first
+    pub files: Vec<PathBuf>,
+    // Original location for every line of the output, None for injected lines
+    line_map: Vec<Option<SourceLocation>>,
+}
+
+impl PreprocessedSource {
+    /// Maps a 1-based line of the preprocessed source back to the original file and line
+    pub fn map_line(&self, line: u32) -> Option<&SourceLocation> {
+        let index = (line as usize).checked_sub(1)?;
+        self.line_map.get(index)?.as_ref()
+    }
+
+    /// Returns the name of the file with the given index
+    pub fn file_name(&self, file: usize) -> Option<String> {
+        self.files.get(file).map(|f| f.display().to_string())
+    }
+
+    /// Rewrites the line references of a compiler info log (e.g. ```0:12``` or ```0(12)```) to ```file:line```
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| match parse_log_location(line) {
+                Some((start, end, reported)) => match self.map_line(reported).and_then(|l| Some((self.file_name(l.file)?, l.line))) {
+                    Some((file, original)) => format!("{}{}:{}{}", &line[..start], file, original, &line[end..]),
+                    None => line.to_string(),
+                },
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Returns the text of a line of one of the original files, as long as it ended up in the preprocessed source
+    pub fn original_line(&self, file: usize, line: u32) -> Option<&str> {
+        let location = SourceLocation { file, line };
+        let index = self.line_map.iter().position(|l| l.as_ref() == Some(&location))?;
+        self.source.lines().nth(index)
+    }
+
+    /// Maps a diagnostic reported for the preprocessed source to the original file and line.
+    /// Diagnostics on injected lines are returned unchanged.
+    pub fn map_diagnostic(&self, diagnostic: &Diagnostic) -> Diagnostic {
+        let mut result = diagnostic.clone();
+        if let Some(location) = self.map_line(diagnostic.line) {
+            result.file = location.file;
+            result.line = location.line;
+        }
+        result
+    }
+
+    /// Formats diagnostics returned by ```map_diagnostic``` with the file names and source lines of the original files
+    pub fn pretty_print(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics
+            .iter()
+            .map(|d| {
+                let name = self.file_name(d.file).unwrap_or_else(|| d.file.to_string());
+                d.pretty(&name, self.original_line(d.file, d.line))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Finds the first ```<source>:<line>``` or ```<source>(<line>)``` reference in a line of a compiler log.
+/// Returns the byte range of the reference and the line number.
+pub(crate) fn parse_log_location(line: &str) -> Option<(usize, usize, u32)> {
+    let bytes = line.as_bytes();
+    let mut start = 0;
+    while start < bytes.len() {
+        if bytes[start].is_ascii_digit() && (start == 0 || !bytes[start - 1].is_ascii_alphanumeric()) {
+            let mut i = start;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            let separator = bytes.get(i).copied();
+            if separator == Some(b':') || separator == Some(b'(') {
+                let line_start = i + 1;
+                let mut j = line_start;
+                while j < bytes.len() && bytes[j].is_ascii_digit() {
+                    j += 1;
+                }
+
+                let closed = separator == Some(b':') || bytes.get(j) == Some(&b')');
+                if j > line_start && closed {
+                    let end = if separator == Some(b'(') { j + 1 } else { j };
+                    let reported = line[line_start..j].parse().ok()?;
+                    return Some((start, end, reported));
+                }
+            }
+            start = i;
+        } else {
+            start += 1;
+        }
+    }
+
+    None
+}
+
+/// # Preprocessor
+/// Resolves ```#include "file"``` directives and injects a ```#version``` header and ```#define```s into GLSL source.
+/// Quoted includes are looked up relative to the including file first and then in the search paths,
+/// ```#include <file>``` only uses the search paths. Files containing ```#pragma once``` are included once.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessor {
+    search_paths: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+    version: Option<String>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        Preprocessor::default()
+    }
+
+    pub fn with_search_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.search_paths.push(path.as_ref().to_path_buf());
+
+        self
+    }
+
+    pub fn with_define(&mut self, name: &str, value: &str) -> &mut Self {
+        self.defines.push((name.to_string(), value.to_string()));
+
+        self
+    }
+
+    /// Sets the ```#version``` of the output (e.g. "450 core"), replacing the version declared in the source
+    pub fn with_version(&mut self, version: &str) -> &mut Self {
+        self.version = Some(version.to_string());
+
+        self
+    }
+
+    /// Returns the defines injected into the source in order
+    pub fn get_defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    /// Loads and preprocesses a file from disk
+    pub fn process_file<P: AsRef<Path>>(&self, path: P) -> Result<PreprocessedSource, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+
+        self.process(&content, path)
+    }
+
+    /// Preprocesses source from memory, ```path``` is used to resolve relative includes and in the line map
+    pub fn process<P: AsRef<Path>>(&self, source: &str, path: P) -> Result<PreprocessedSource, Error> {
+        // Canonicalized like includes so the root is recognized when it is included again,
+        // sources from memory do not need to exist on disk
+        let path = path.as_ref();
+        let mut state = ProcessState::default();
+        state.files.push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        self.expand(source, 0, &mut state, &mut vec![0])?;
+
+        // Find the version declared in the source, it has to stay the first statement
+        let declared = state.lines.iter().position(|(text, _)| text.trim_start().starts_with("#version"));
+        let version = match (&self.version, declared) {
+            (Some(version), Some(index)) => {
+                state.lines.remove(index);
+                Some((format!("#version {}", version), None))
+            }
+            (Some(version), None) => Some((format!("#version {}", version), None)),
+            (None, Some(index)) => Some(state.lines.remove(index)),
+            (None, None) => None,
+        };
+
+        let mut lines: Vec<(String, Option<SourceLocation>)> = Vec::new();
+        lines.extend(version);
+        lines.extend(self.defines.iter().map(|(name, value)| (format!("#define {} {}", name, value), None)));
+        lines.extend(state.lines);
+
+        let mut result = PreprocessedSource {
+            files: state.files,
+            ..Default::default()
+        };
+        for (text, location) in lines {
+            result.source.push_str(&text);
+            result.source.push('\n');
+            result.line_map.push(location);
+        }
+
+        Ok(result)
+    }
+
+    fn expand(&self, source: &str, file: usize, state: &mut ProcessState, stack: &mut Vec<usize>) -> Result<(), Error> {
+        for (index, text) in source.lines().enumerate() {
+            let location = SourceLocation { file, line: index as u32 + 1 };
+            let directive = text.trim_start();
+
+            if directive.starts_with("#pragma") && directive[7..].trim() == "once" {
+                state.once.push(file);
+                continue;
+            }
+
+            if !directive.starts_with("#include") {
+                state.lines.push((text.to_string(), Some(location)));
+                continue;
+            }
+
+            let current = state.files[file].clone();
+            let include = self.resolve_include(&directive[8..], &current)
+                .map_err(|msg| Error::Preprocessor(format!("{}:{}: {}", current.display(), location.line, msg)))?;
+
+            let included = match state.files.iter().position(|f| *f == include) {
+                Some(existing) => existing,
+                None => {
+                    state.files.push(include.clone());
+                    state.files.len() - 1
+                }
+            };
+
+            if stack.contains(&included) {
+                return Err(Error::Preprocessor(format!("{}:{}: recursive include of \"{}\"", current.display(), location.line, include.display())));
+            }
+            if state.once.contains(&included) {
+                continue;
+            }
+
+            let content = std::fs::read_to_string(&include)?;
+            stack.push(included);
+            self.expand(&content, included, state, stack)?;
+            stack.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the argument of an include directive to a file on disk. The path is canonicalized,
+    /// so a file reached through different relative paths or search paths is recognized as the same file.
+    fn resolve_include(&self, argument: &str, current: &Path) -> Result<PathBuf, String> {
+        let argument = argument.trim();
+        let (name, relative) = if argument.starts_with('"') && argument[1..].contains('"') {
+            (&argument[1..argument[1..].find('"').unwrap() + 1], true)
+        } else if argument.starts_with('<') && argument.contains('>') {
+            (&argument[1..argument.find('>').unwrap()], false)
+        } else {
+            return Err(format!("malformed include directive \"{}\"", argument));
+        };
+
+        let local = match (relative, current.parent()) {
+            (true, Some(dir)) => Some(dir.join(name)),
+            (true, None) => Some(PathBuf::from(name)),
+            _ => None,
+        };
+
+        local
+            .into_iter()
+            .chain(self.search_paths.iter().map(|p| p.join(name)))
+            .find(|p| p.is_file())
+            .ok_or_else(|| format!("could not find include \"{}\"", name))
+            .and_then(|p| p.canonicalize().map_err(|err| format!("could not resolve include \"{}\": {}", name, err)))
+    }
+}
+
+#[derive(Default)]
+struct ProcessState {
+    files: Vec<PathBuf>,
+    lines: Vec<(String, Option<SourceLocation>)>,
+    // Files that contained #pragma once
+    once: Vec<usize>,
+}
+
+#[cfg(test)]
+mod preprocessor_tests {
+    use super::*;
+
+    fn write_files(dir: &str, files: &[(&str, &str)]) -> PathBuf {
+        // Every test run gets its own directory so parallel runs do not overwrite each other's files
+        let dir = std::env::temp_dir().join(format!("glw_{}", std::process::id())).join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn resolves_includes_and_maps_lines() {
+        let dir = write_files("preprocessor_includes", &[
+            ("main.glsl", "#version 450\n#include \"common.glsl\"\nvoid main() {}\n"),
+            ("common.glsl", "#pragma once\nfloat square(float x) { return x * x; }\n"),
+        ]);
+
+        let result = Preprocessor::new()
+            .with_define("USE_LIGHTING", "1")
+            .process_file(dir.join("main.glsl"))
+            .unwrap();
+
+        assert_eq!(result.source, "#version 450\n#define USE_LIGHTING 1\nfloat square(float x) { return x * x; }\nvoid main() {}\n");
+        assert_eq!(result.map_line(1), Some(&SourceLocation { file: 0, line: 1 }));
+        assert_eq!(result.map_line(2), None);
+        assert_eq!(result.map_line(3), Some(&SourceLocation { file: 1, line: 2 }));
+        assert_eq!(result.map_line(4), Some(&SourceLocation { file: 0, line: 3 }));
+    }
+
+    #[test]
+    fn version_override_and_log_mapping() {
+        let dir = write_files("preprocessor_version", &[
+            ("main.glsl", "#version 330\nvoid main() { oops }\n"),
+        ]);
+
+        let result = Preprocessor::new()
+            .with_version("450 core")
+            .process_file(dir.join("main.glsl"))
+            .unwrap();
+
+        assert_eq!(result.source, "#version 450 core\nvoid main() { oops }\n");
+
+        let file = dir.join("main.glsl").display().to_string();
+        assert_eq!(result.map_log("0:2(15): error: syntax error"), format!("{}:2(15): error: syntax error", file));
+        assert_eq!(result.map_log("0(2) : error C0000: syntax error"), format!("{}:2 : error C0000: syntax error", file));
+    }
+
+    #[test]
+    fn reports_missing_and_recursive_includes() {
+        let dir = write_files("preprocessor_errors", &[
+            ("missing.glsl", "#include \"nope.glsl\"\n"),
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n"),
+        ]);
+
+        assert!(matches!(Preprocessor::new().process_file(dir.join("missing.glsl")), Err(Error::Preprocessor(_))));
+        assert!(matches!(Preprocessor::new().process_file(dir.join("a.glsl")), Err(Error::Preprocessor(_))));
+
+        // The cycle is found when b includes the root again, even when the root was given through another spelling
+        write_files("preprocessor_errors/sub", &[]);
+        match Preprocessor::new().process_file(dir.join("sub").join("..").join("a.glsl")) {
+            Err(Error::Preprocessor(msg)) => assert!(msg.contains("b.glsl:1: recursive include"), "{}", msg),
+            _ => panic!("Expected a recursive include error"),
+        }
+    }
+
+    #[test]
+    fn identifies_includes_spelled_differently() {
+        let dir = write_files("preprocessor_spelling", &[
+            ("main.glsl", "#include \"lib/common.glsl\"\n#include \"./lib/../lib/common.glsl\"\n#include <common.glsl>\nvoid main() {}\n"),
+        ]);
+        write_files("preprocessor_spelling/lib", &[("common.glsl", "#pragma once\nfloat square(float x) { return x * x; }\n")]);
+
+        let result = Preprocessor::new()
+            .with_search_path(dir.join("lib"))
+            .process_file(dir.join("main.glsl"))
+            .unwrap();
+
+        assert_eq!(result.source, "float square(float x) { return x * x; }\nvoid main() {}\n");
+        assert_eq!(result.files.len(), 2);
+    }
+}
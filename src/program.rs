@@ -1,9 +1,11 @@
 use super::*;
 
-use std::ffi::CString;
+use std::fmt;
 use std::rc::Rc;
 
-#[derive(Eq, PartialEq)]
+use super::hotreload::{HotReload, ReloadCallback};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PipelineType {
     Graphics,
     Compute,
@@ -16,12 +18,126 @@ pub trait Pipeline {
 
     // Returns the API specific handle
     fn get(self: &Self) -> *const std::ffi::c_void;
+
+    /// Returns the uniform locations of the linked program
+    fn get_uniform_cache(self: &Self) -> Rc<UniformCache>;
+
+    /// Returns the number of vertices per patch for pipelines with tessellation stages
+    fn get_patch_vertices(self: &Self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the programs of every stage for pipelines made of separable programs, empty for linked programs
+    fn get_stage_uniforms(self: &Self) -> Vec<StageUniforms> {
+        Vec::new()
+    }
+}
+
+/// Program and uniform locations of one stage of a pipeline made of separable programs
+#[derive(Clone, Debug)]
+pub struct StageUniforms {
+    pub stage: ShaderType,
+    pub program: GLuint,
+    pub uniforms: Rc<UniformCache>,
+}
+
+/// Invalid combinations of shader stages passed to a ```PipelineBuilder```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StageError {
+    /// A graphics pipeline needs at least a vertex shader
+    MissingVertexShader,
+    /// A compute pipeline needs a compute shader
+    MissingComputeShader,
+    /// A compute shader can not be linked together with graphics stages
+    MixedComputeAndGraphics,
+    /// A tessellation control shader needs a tessellation evaluation shader
+    MissingTessEvaluationShader,
+    /// Patch vertices were set on a pipeline without tessellation stages
+    PatchVerticesWithoutTessellation,
+    /// A shader was set for a stage it was not created for
+    MismatchedStage { expected: ShaderType, found: ShaderType },
+    /// More than one program was given for the same stage of a program pipeline
+    DuplicateStage(ShaderType),
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StageError::MissingVertexShader => write!(f, "a graphics pipeline requires a vertex shader"),
+            StageError::MissingComputeShader => write!(f, "a compute pipeline requires a compute shader"),
+            StageError::MixedComputeAndGraphics => write!(f, "a compute shader can not be combined with graphics stages, compute pipelines are built with build_compute"),
+            StageError::MissingTessEvaluationShader => write!(f, "a tessellation control shader requires a tessellation evaluation shader"),
+            StageError::PatchVerticesWithoutTessellation => write!(f, "patch vertices require a tessellation evaluation shader"),
+            StageError::MismatchedStage { expected, found } => write!(f, "a {:?} shader was set as {:?} shader", found, expected),
+            StageError::DuplicateStage(stage) => write!(f, "more than one {:?} program was given", stage),
+        }
+    }
+}
+
+/// Links the program and checks the link status. Returns the program info log when linking failed.
+pub(crate) fn link_program(id: GLuint) -> Result<(), String> {
+    unsafe {
+        gl::LinkProgram(id);
+
+        let mut success: GLint = 0;
+        gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        match success {
+            0 => Err(program_info_log(id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the info log of the last link of the program, which can hold warnings when linking succeeded
+pub(crate) fn program_info_log(id: GLuint) -> String {
+    unsafe {
+        let mut log_size: GLint = 0;
+        gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut log_size);
+        let mut msg: Vec<u8> = vec![0; log_size.max(0) as usize];
+
+        let mut new_length = 0;
+        gl::GetProgramInfoLog(id, log_size, &mut new_length, msg.as_mut_ptr() as *mut GLchar);
+        msg.truncate(new_length.max(0) as usize);
+
+        String::from_utf8_lossy(&msg).into_owned()
+    }
+}
+
+/// Creates and links a program from the given shaders. The program is deleted again when linking fails.
+pub(crate) fn link_shaders(shaders: &[Rc<Shader>]) -> Result<GLuint, Error> {
+    unsafe {
+        let id = gl::CreateProgram();
+        for shader in shaders {
+            gl::AttachShader(id, shader.get_id());
+        }
+
+        match link_program(id) {
+            Ok(()) => Ok(id),
+            Err(log) => {
+                gl::DeleteProgram(id);
+                Err(Error::ProgramLink {
+                    log,
+                    stages: shaders.iter().map(|s| s.get_type()).collect(),
+                })
+            }
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct GraphicsPipeline {
     // Open GL program ID
     id: GLuint,
+
+    // Interface of the program, queried at link time
+    reflection: ProgramReflection,
+    uniforms: Rc<UniformCache>,
+
+    // Vertices per patch, only set for pipelines with tessellation stages
+    patch_vertices: Option<u32>,
+
+    // Set when the pipeline was built with hot reloading enabled
+    hot_reload: Option<HotReload>,
 }
 
 // Implement the generic interface for pipelines
@@ -33,6 +149,14 @@ impl Pipeline for GraphicsPipeline {
     fn get(self: &Self) -> *const std::ffi::c_void {
         self.id as *const _
     }
+
+    fn get_uniform_cache(self: &Self) -> Rc<UniformCache> {
+        self.uniforms.clone()
+    }
+
+    fn get_patch_vertices(self: &Self) -> Option<u32> {
+        self.patch_vertices
+    }
 }
 
 impl GraphicsPipeline {
@@ -40,6 +164,10 @@ impl GraphicsPipeline {
         unsafe {
             GraphicsPipeline {
                 id: gl::CreateProgram(),
+                reflection: ProgramReflection::default(),
+                uniforms: Rc::default(),
+                patch_vertices: None,
+                hot_reload: None,
             }
         }
     }
@@ -50,10 +178,51 @@ impl GraphicsPipeline {
         }
     }
 
-    fn link(&mut self) {
+    fn link(&mut self, cache: Option<(&ProgramCache, u64)>) -> Result<(), String> {
+        match cache {
+            Some((cache, key)) => cache.link(self.id, key, link_program)?,
+            None => link_program(self.id)?,
+        }
+
+        self.reflection = ProgramReflection::query(self.id);
+        self.uniforms = Rc::new(UniformCache::new(&self.reflection));
+
+        Ok(())
+    }
+
+    /// Returns the active uniforms, blocks and vertex inputs of the linked program
+    pub fn reflect(&self) -> &ProgramReflection {
+        &self.reflection
+    }
+
+    /// Returns the program info log of the last link, e.g. warnings reported while linking succeeded
+    pub fn get_link_log(&self) -> String {
+        program_info_log(self.id)
+    }
+
+    /// Returns a handle to an active uniform which can be passed to ```set_uniform_handle```
+    pub fn get_uniform_handle(&self, name: &str) -> Option<UniformHandle> {
+        self.uniforms.get(name)
+    }
+
+    /// Recompiles and relinks the pipeline when one of its shader files changed on disk.
+    /// Returns true when the program was replaced. Uniform handles and command lists recorded with the old
+    /// program are invalid afterwards and need to be retrieved and recorded again.
+    /// Does nothing for pipelines built without ```PipelineBuilder::with_hot_reload```.
+    pub fn poll_reload(&mut self) -> bool {
+        let program = match self.hot_reload.as_mut().and_then(|r| r.poll()) {
+            Some(program) => program,
+            None => return false,
+        };
+
         unsafe {
-            gl::LinkProgram(self.id);
+            gl::DeleteProgram(self.id);
         }
+        self.id = program;
+        self.reflection = ProgramReflection::query(self.id);
+        self.uniforms = Rc::new(UniformCache::new(&self.reflection));
+
+        true
     }
 }
 
@@ -65,11 +234,178 @@ impl Drop for GraphicsPipeline {
     }
 }
 
+/// # Compute Pipeline
+/// A program that only contains a compute shader. Can be bound to a command list to dispatch work.
+#[derive(Default)]
+pub struct ComputePipeline {
+    // Open GL program ID
+    id: GLuint,
+
+    // Local work group size declared in the compute shader
+    work_group_size: [u32; 3],
+
+    // Interface of the program, queried at link time
+    reflection: ProgramReflection,
+    uniforms: Rc<UniformCache>,
+
+    // Set when the pipeline was built with hot reloading enabled
+    hot_reload: Option<HotReload>,
+}
+
+impl Pipeline for ComputePipeline {
+    fn get_type(self: &Self) -> PipelineType {
+        PipelineType::Compute
+    }
+
+    fn get(self: &Self) -> *const std::ffi::c_void {
+        self.id as *const _
+    }
+
+    fn get_uniform_cache(self: &Self) -> Rc<UniformCache> {
+        self.uniforms.clone()
+    }
+}
+
+impl ComputePipeline {
+    fn new() -> ComputePipeline {
+        unsafe {
+            ComputePipeline {
+                id: gl::CreateProgram(),
+                work_group_size: [0; 3],
+                reflection: ProgramReflection::default(),
+                uniforms: Rc::default(),
+                hot_reload: None,
+            }
+        }
+    }
+
+    fn attach(&mut self, shader: &Shader) {
+        unsafe {
+            gl::AttachShader(self.id, shader.get_id());
+        }
+    }
+
+    fn link(&mut self, cache: Option<(&ProgramCache, u64)>) -> Result<(), String> {
+        match cache {
+            Some((cache, key)) => cache.link(self.id, key, link_program)?,
+            None => link_program(self.id)?,
+        }
+        self.query_interface();
+
+        Ok(())
+    }
+
+    fn query_interface(&mut self) {
+        unsafe {
+            let mut size: [GLint; 3] = [0; 3];
+            gl::GetProgramiv(self.id, gl::COMPUTE_WORK_GROUP_SIZE, size.as_mut_ptr());
+            self.work_group_size = [size[0] as u32, size[1] as u32, size[2] as u32];
+        }
+
+        self.reflection = ProgramReflection::query(self.id);
+        self.uniforms = Rc::new(UniformCache::new(&self.reflection));
+    }
+
+    /// Returns the local work group size (```local_size_x/y/z```) of the linked compute shader
+    pub fn get_work_group_size(&self) -> [u32; 3] {
+        self.work_group_size
+    }
+
+    /// Returns the active uniforms and blocks of the linked program
+    pub fn reflect(&self) -> &ProgramReflection {
+        &self.reflection
+    }
+
+    /// Returns the program info log of the last link, see ```GraphicsPipeline::get_link_log```
+    pub fn get_link_log(&self) -> String {
+        program_info_log(self.id)
+    }
+
+    /// Returns a handle to an active uniform which can be passed to ```set_uniform_handle```
+    pub fn get_uniform_handle(&self, name: &str) -> Option<UniformHandle> {
+        self.uniforms.get(name)
+    }
+
+    /// Recompiles and relinks the pipeline when its shader file changed on disk, see ```GraphicsPipeline::poll_reload```
+    pub fn poll_reload(&mut self) -> bool {
+        let program = match self.hot_reload.as_mut().and_then(|r| r.poll()) {
+            Some(program) => program,
+            None => return false,
+        };
+
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+        self.id = program;
+        self.query_interface();
+
+        true
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
+
+/// Checks that the shaders set on a builder form a legal graphics pipeline.
+/// ```stages``` pairs the stage of every set slot with the type of the shader set on it.
+pub(crate) fn validate_graphics_stages(stages: &[(ShaderType, ShaderType)], patch_vertices: Option<u32>) -> Result<(), StageError> {
+    validate_slots(stages)?;
+
+    let has = |stage| stages.iter().any(|(slot, _)| *slot == stage);
+    if has(ShaderType::Compute) {
+        return Err(StageError::MixedComputeAndGraphics);
+    }
+    if !has(ShaderType::Vertex) {
+        return Err(StageError::MissingVertexShader);
+    }
+    if has(ShaderType::TessControl) && !has(ShaderType::TessEvaluation) {
+        return Err(StageError::MissingTessEvaluationShader);
+    }
+    if patch_vertices.is_some() && !has(ShaderType::TessEvaluation) {
+        return Err(StageError::PatchVerticesWithoutTessellation);
+    }
+
+    Ok(())
+}
+
+/// Checks that the shaders set on a builder form a legal compute pipeline
+pub(crate) fn validate_compute_stages(stages: &[(ShaderType, ShaderType)]) -> Result<(), StageError> {
+    validate_slots(stages)?;
+
+    match stages {
+        [(ShaderType::Compute, _)] => Ok(()),
+        [] => Err(StageError::MissingComputeShader),
+        _ => Err(StageError::MixedComputeAndGraphics),
+    }
+}
+
+fn validate_slots(stages: &[(ShaderType, ShaderType)]) -> Result<(), StageError> {
+    match stages.iter().find(|(slot, shader)| slot != shader) {
+        Some((expected, found)) => Err(StageError::MismatchedStage { expected: *expected, found: *found }),
+        None => Ok(()),
+    }
+}
+
 #[derive(Default)]
 pub struct PipelineBuilder {
     vshader: Option<Rc<Shader>>,
+    tcshader: Option<Rc<Shader>>,
+    teshader: Option<Rc<Shader>>,
+    gshader: Option<Rc<Shader>>,
     fshader: Option<Rc<Shader>>,
     cshader: Option<Rc<Shader>>,
+
+    patch_vertices: Option<u32>,
+
+    hot_reload: bool,
+    reload_callback: Option<ReloadCallback>,
+
+    program_cache: Option<Rc<ProgramCache>>,
 }
 
 impl PipelineBuilder {
@@ -77,55 +413,185 @@ impl PipelineBuilder {
         PipelineBuilder::default()
     }
 
-    pub fn with_vertex_shader(&mut self, shader: Shader) -> &mut Self {
-        self.vshader = Some(Rc::new(shader));
+    pub fn with_vertex_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.vshader = Some(shader.into());
 
         self
     }
 
-    pub fn with_fragment_shader(&mut self, shader: Shader) -> &mut Self {
-        self.fshader = Some(Rc::new(shader));
+    pub fn with_fragment_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.fshader = Some(shader.into());
 
         self
     }
 
-    pub fn with_compute_shader(&mut self, shader: Shader) -> &mut Self {
-        self.cshader = Some(Rc::new(shader));
+    pub fn with_compute_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.cshader = Some(shader.into());
 
         self
     }
 
-    pub fn build(&self) -> GraphicsPipeline {
-        let mut result = GraphicsPipeline::new();
+    pub fn with_geometry_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.gshader = Some(shader.into());
+
+        self
+    }
+
+    pub fn with_tess_control_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.tcshader = Some(shader.into());
+
+        self
+    }
+
+    pub fn with_tess_evaluation_shader<S: Into<Rc<Shader>>>(&mut self, shader: S) -> &mut Self {
+        self.teshader = Some(shader.into());
+
+        self
+    }
 
-        assert!(self.cshader.is_some() || ( (self.fshader.is_some() || self.vshader.is_some()) && self.cshader.is_none() ), "Can not have a compute shader bound to a program that has a vertex shader or a fragment shader!");
+    /// Sets the number of vertices per patch used when drawing with the pipeline, defaults to 3.
+    /// Requires a tessellation evaluation shader.
+    pub fn with_patch_vertices(&mut self, count: u32) -> &mut Self {
+        self.patch_vertices = Some(count);
 
-        if let Some(ref shader) = self.vshader {
-            result.attach(&shader);
+        self
+    }
+
+    /// Enables hot reloading for pipelines built by this builder. Shaders loaded with ```Shader::load_from_file```
+    /// are watched and recompiled when ```poll_reload``` is called on the pipeline after one of their files changed.
+    /// When recompiling or relinking fails the previous program is kept and ```callback``` is invoked with the error.
+    pub fn with_hot_reload<F: Fn(&Error) + 'static>(&mut self, callback: F) -> &mut Self {
+        self.hot_reload = true;
+        self.reload_callback = Some(Rc::new(callback));
+
+        self
+    }
+
+    /// Loads linked programs from ```cache``` when possible and stores newly linked programs in it.
+    /// Entries rejected by the driver are relinked from the shaders and replaced.
+    pub fn with_program_cache(&mut self, cache: Rc<ProgramCache>) -> &mut Self {
+        self.program_cache = Some(cache);
+
+        self
+    }
+
+    /// Returns the stages of all shaders set on the builder in pipeline order
+    pub fn get_stages(&self) -> Vec<ShaderType> {
+        self.slots().iter().map(|(_, shader)| shader.get_type()).collect()
+    }
+
+    /// Builds and links a graphics pipeline from the vertex, tessellation, geometry and fragment shaders.
+    /// Use ```build_compute``` for pipelines with a compute shader.
+    pub fn build(&self) -> Result<GraphicsPipeline, Error> {
+        let slots = self.slots();
+        let stages: Vec<(ShaderType, ShaderType)> = slots.iter().map(|(slot, shader)| (*slot, shader.get_type())).collect();
+        validate_graphics_stages(&stages, self.patch_vertices).map_err(Error::InvalidStages)?;
+        if self.patch_vertices == Some(0) {
+            return Err(Error::InvalidArgument("a patch needs at least one vertex".to_string()));
         }
 
-        if let Some(ref shader) = self.fshader {
-            result.attach(&shader);
+        let mut result = GraphicsPipeline::new();
+        for (_, shader) in &slots {
+            result.attach(shader);
+        }
+        if self.teshader.is_some() {
+            result.patch_vertices = Some(self.patch_vertices.unwrap_or(3));
         }
 
+        let shaders = [&self.vshader, &self.tcshader, &self.teshader, &self.gshader, &self.fshader];
+        let cache = self.program_cache(&shaders);
+        result.link(cache).map_err(|log| Error::ProgramLink { log, stages: self.get_stages() })?;
+        result.hot_reload = self.hot_reload(&shaders);
+
+        Ok(result)
+    }
+
+    /// Builds and links a compute pipeline from the compute shader
+    pub fn build_compute(&self) -> Result<ComputePipeline, Error> {
+        let stages: Vec<(ShaderType, ShaderType)> = self.slots().iter().map(|(slot, shader)| (*slot, shader.get_type())).collect();
+        validate_compute_stages(&stages).map_err(Error::InvalidStages)?;
+
+        let mut result = ComputePipeline::new();
         if let Some(ref shader) = self.cshader {
-            result.attach(&shader);
+            result.attach(shader);
         }
 
-        result.link();
+        let cache = self.program_cache(&[&self.cshader]);
+        result.link(cache).map_err(|log| Error::ProgramLink { log, stages: self.get_stages() })?;
+        result.hot_reload = self.hot_reload(&[&self.cshader]);
 
-        result
+        Ok(result)
     }
 }
 
+impl PipelineBuilder {
+    /// Returns the set shaders together with the stage of the slot they were set on
+    fn slots(&self) -> Vec<(ShaderType, &Rc<Shader>)> {
+        [
+            (ShaderType::Vertex, &self.vshader),
+            (ShaderType::TessControl, &self.tcshader),
+            (ShaderType::TessEvaluation, &self.teshader),
+            (ShaderType::Geometry, &self.gshader),
+            (ShaderType::Fragment, &self.fshader),
+            (ShaderType::Compute, &self.cshader),
+        ]
+        .iter()
+        .filter_map(|(slot, shader)| shader.as_ref().map(|s| (*slot, s)))
+        .collect()
+    }
+
+    fn hot_reload(&self, shaders: &[&Option<Rc<Shader>>]) -> Option<HotReload> {
+        if !self.hot_reload {
+            return None;
+        }
+
+        let shaders = shaders.iter().filter_map(|s| (*s).clone()).collect();
+        Some(HotReload::new(shaders, self.reload_callback.clone()))
+    }
+
+    /// Returns the cache and the key of the program built from ```shaders``` when a cache is set
+    fn program_cache(&self, shaders: &[&Option<Rc<Shader>>]) -> Option<(&ProgramCache, u64)> {
+        let cache = self.program_cache.as_ref()?;
+        let shaders: Vec<&Shader> = shaders.iter().filter_map(|s| s.as_deref()).collect();
+
+        cache.key(&shaders).map(|key| (cache.as_ref(), key))
+    }
+}
+
+/// # Command List
+/// Records rendering commands. Nothing is submitted to OpenGL until the list is passed to
+/// ```GLContext::execute_command_list```, a recorded list can be executed any number of times.
 pub trait CommandList {
     fn get(self: &Self) -> *const std::ffi::c_void;
 
-    fn bind_buffer(&mut self, buffer: &impl buffers::BufferResource, slot: u32);
+    /// Returns the recorded commands in submission order
+    fn commands(&self) -> &[Command];
+
+    /// Removes all recorded commands so the list can be recorded again
+    fn reset(&mut self);
+
+    /// Binds a uniform, storage or atomic counter buffer to the binding point ```slot```, other buffers are rejected
+    fn bind_buffer(&mut self, buffer: &impl buffers::BufferResource, slot: u32) -> Result<(), Error>;
+
+    /// Binds part of a buffer to a slot, e.g. the region of a ```StreamBuffer``` written this frame.
+    /// The offset needs to be a multiple of the offset alignment of the binding target, ranges outside of the buffer are rejected.
+    fn bind_buffer_range(&mut self, buffer: &impl buffers::BufferResource, slot: u32, range: buffers::BufferRange) -> Result<(), Error>;
 
     fn bind_texture(&mut self, rt: &RenderTarget);
 
-    fn set_uniform(&mut self, uniform_name: &str, uni: Uniform);
+    /// Sets a uniform on the bound pipeline. The location is looked up in the uniform cache of the pipeline,
+    /// uniforms that are not active are ignored unless strict mode is enabled.
+    fn set_uniform(&mut self, uniform_name: &str, uni: Uniform) -> Result<(), Error>;
+
+    /// Sets a uniform of the bound pipeline through a handle retrieved from that pipeline
+    fn set_uniform_handle(&mut self, handle: UniformHandle, uni: Uniform) -> Result<(), Error>;
+
+    /// Sets a uniform on one stage of a bound program pipeline, see ```ProgramPipeline```
+    fn set_stage_uniform(&mut self, stage: ShaderType, uniform_name: &str, uni: Uniform) -> Result<(), Error>;
+
+    /// Enables strict mode, in which unknown uniforms, type mismatches and arrays longer than the uniform are reported as errors.
+    /// Otherwise unknown uniforms are ignored and values are set without checking their type.
+    fn set_strict(&mut self, strict: bool);
 
     // #TODO: Test
     fn set_sampler(&mut self, sampler: GLuint);
@@ -133,6 +599,14 @@ pub trait CommandList {
     /// Binds a pipeline to the context.
     fn bind_pipeline(&mut self, pipeline: &impl program::Pipeline);
 
+    /// Binds the pipeline of a shader variant, building it first when the variant was not used before
+    fn bind_variant(&mut self, variants: &ShaderVariantSet, key: &VariantKey) -> Result<(), Error> {
+        let pipeline = variants.get(key)?;
+        self.bind_pipeline(&*pipeline);
+
+        Ok(())
+    }
+
     /// Set's the current active viewport
     fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
 
@@ -142,14 +616,44 @@ pub trait CommandList {
     /// Binds a render target for drawing
     fn bind_rt(&mut self, rt: &RenderTarget);
 
-    fn dispatch(&mut self, groups_x: u32, groups_y: u32, groups_z: u32);
+    /// Dispatches compute work groups, fails when no compute pipeline is bound
+    fn dispatch(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<(), Error>;
+
+    /// Dispatches compute work groups with the group counts read from the indirect ```buffer``` at byte ```offset```, which needs to be a multiple of 4.
+    /// Fails when no compute pipeline is bound.
+    fn dispatch_indirect(&mut self, buffer: &impl buffers::BufferResource, offset: usize) -> Result<(), Error>;
 
     fn memory_barrier(&mut self, barrier: MemoryBarrier);
+
+    /// Draws all vertices of the geometry without using its index buffer.
+    /// Like every draw, fails when no graphics pipeline is bound or when ```topology``` is ```Patches``` but the bound pipeline has no tessellation stages or the other way around.
+    fn draw(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology) -> Result<(), Error>;
+
+    /// Draws the geometry using its index buffer, fails when the geometry has none
+    fn draw_indexed(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology) -> Result<(), Error>;
+
+    /// Draws ```instances``` copies of the geometry, indexed when the geometry has an index buffer
+    fn draw_instanced(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology, instances: u32) -> Result<(), Error>;
+
+    /// Draws the geometry with arguments sourced from the indirect ```buffer``` at byte ```offset```.
+    /// The buffer needs to contain a DrawElementsIndirectCommand for indexed geometry and a DrawArraysIndirectCommand otherwise,
+    /// the offset needs to be a multiple of 4.
+    fn draw_indirect(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology, buffer: &impl buffers::BufferResource, offset: usize) -> Result<(), Error>;
 }
 
 #[derive(Default)]
 pub struct GraphicsCommandList {
-    current_pipeline: GLuint,
+    commands: Vec<Command>,
+
+    // Type and uniforms of the last pipeline bound while recording
+    current_pipeline_type: Option<PipelineType>,
+    current_uniforms: Option<Rc<UniformCache>>,
+    current_patch_vertices: Option<u32>,
+    // Stages of the bound program pipeline, empty when a linked program is bound
+    current_stages: Vec<StageUniforms>,
+    current_program_pipeline: GLuint,
+
+    strict: bool,
 }
 
 impl CommandList for GraphicsCommandList {
@@ -157,111 +661,623 @@ impl CommandList for GraphicsCommandList {
         std::ptr::null()
     }
 
-    fn bind_buffer(&mut self, buffer: &impl buffers::BufferResource, slot: u32) {
-        unsafe {
-            gl::BindBufferBase(
-                gl::SHADER_STORAGE_BUFFER,
+    fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    fn reset(&mut self) {
+        self.commands.clear();
+        self.current_pipeline_type = None;
+        self.current_uniforms = None;
+        self.current_patch_vertices = None;
+        self.current_stages.clear();
+        self.current_program_pipeline = 0;
+    }
+
+    fn bind_buffer(&mut self, buffer: &impl buffers::BufferResource, slot: u32) -> Result<(), Error> {
+        let usage = Self::indexed_usage(buffer)?;
+
+        self.commands.push(match usage {
+            buffers::BufferUsage::Storage => Command::BindBuffer {
+                buffer: buffer.get_resource() as GLuint,
                 slot,
-                buffer.get_resource() as GLuint,
-            );
+            },
+            _ => Command::BindBufferBase {
+                target: usage.get(),
+                buffer: buffer.get_resource() as GLuint,
+                slot,
+            },
+        });
+
+        Ok(())
+    }
+
+    fn bind_buffer_range(&mut self, buffer: &impl buffers::BufferResource, slot: u32, range: buffers::BufferRange) -> Result<(), Error> {
+        let usage = Self::indexed_usage(buffer)?;
+        match range.offset.checked_add(range.size) {
+            Some(end) if end <= buffer.get_buffer_size() => {}
+            _ => return Err(Error::InvalidArgument(format!("{} bytes at offset {} do not lie within the buffer", range.size, range.offset))),
         }
+
+        self.commands.push(Command::BindBufferRange {
+            target: usage.get(),
+            buffer: buffer.get_resource() as GLuint,
+            slot,
+            offset: range.offset,
+            size: range.size,
+        });
+
+        Ok(())
     }
 
     fn bind_texture(&mut self, rt: &RenderTarget) {
-        unsafe {
-            gl::BindImageTexture(
-                0,
-                rt.get_texture(),
-                0,
-                false as u8,
-                0,
-                gl::WRITE_ONLY,
-                gl::RGBA8,
-            );
+        self.commands.push(Command::BindTexture {
+            texture: rt.get_texture(),
+        });
+    }
+
+    fn set_uniform(&mut self, uniform_name: &str, uni: Uniform) -> Result<(), Error> {
+        // Program pipelines set the uniform on the first stage that declares it
+        if let Some(stage) = self.current_stages.iter().find(|s| s.uniforms.get(uniform_name).is_some()) {
+            let stage = stage.stage;
+            return self.set_stage_uniform(stage, uniform_name, uni);
+        }
+
+        let handle = self.current_uniforms.as_ref().and_then(|uniforms| uniforms.get(uniform_name));
+
+        match handle {
+            Some(handle) => self.set_uniform_handle(handle, uni),
+            None if self.strict => Err(Error::UnknownUniform(uniform_name.to_string())),
+            None => Ok(()),
         }
     }
 
-    fn set_uniform(&mut self, uniform_name: &str, uni: Uniform) {
-        unsafe {
-            let c_string: CString = CString::new(uniform_name).unwrap();
-            let loc =
-                gl::GetUniformLocation(self.current_pipeline, c_string.as_ptr() as *const GLchar);
-            match loc {
-                -1 => {}
-                _ => match uni {
-                    Uniform::Float(v) => gl::Uniform1f(loc, v),
-                    Uniform::Int(v) => gl::Uniform1i(loc, v),
-                    Uniform::Vec2(x, y) => {
-                        gl::Uniform2f(loc, x, y);
-                    }
-                    Uniform::Sampler2D(v) => {
-                        self.set_sampler(v);
-
-                        gl::Uniform1i(loc, v as i32);
-                    }
-                },
+    fn set_uniform_handle(&mut self, handle: UniformHandle, uni: Uniform) -> Result<(), Error> {
+        // Outside of strict mode the value is passed on unchecked, as glUniform* would
+        if self.strict && !uni.matches(handle.get_type()) {
+            return Err(Error::UniformTypeMismatch {
+                location: handle.get_location(),
+                expected: handle.get_type(),
+            });
+        }
+        if self.strict && uni.element_count() > handle.get_array_size() as usize {
+            return Err(Error::InvalidArgument(format!(
+                "{} elements were set on the uniform at location {} which has {}",
+                uni.element_count(),
+                handle.get_location(),
+                handle.get_array_size()
+            )));
+        }
+
+        self.commands.push(Command::SetUniform {
+            location: handle.get_location(),
+            uniform: uni,
+        });
+
+        Ok(())
+    }
+
+    fn set_stage_uniform(&mut self, stage: ShaderType, uniform_name: &str, uni: Uniform) -> Result<(), Error> {
+        let target = self.current_stages.iter().find(|s| s.stage == stage).and_then(|s| Some((s.program, s.uniforms.get(uniform_name)?)));
+
+        match target {
+            Some((program, handle)) => {
+                self.commands.push(Command::SetActiveProgram {
+                    pipeline: self.current_program_pipeline,
+                    program,
+                });
+                self.set_uniform_handle(handle, uni)
             }
+            None if self.strict => Err(Error::UnknownUniform(uniform_name.to_string())),
+            None => Ok(()),
         }
     }
 
+    fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
     // #TODO: Test
     fn set_sampler(&mut self, sampler: GLuint) {
-        unsafe {
-            // Bind our input texture
-            gl::ActiveTexture(gl::TEXTURE0 + sampler);
-            gl::BindTexture(gl::TEXTURE_2D, sampler);
-        }
+        self.commands.push(Command::SetSampler(sampler));
     }
 
     /// Binds a pipeline to the context.
     fn bind_pipeline(&mut self, pipeline: &impl program::Pipeline) {
-        assert!(pipeline.get_type() == program::PipelineType::Graphics);
-        unsafe {
-            gl::UseProgram(pipeline.get() as GLuint);
+        self.current_pipeline_type = Some(pipeline.get_type());
+        self.current_uniforms = Some(pipeline.get_uniform_cache());
+        self.current_patch_vertices = pipeline.get_patch_vertices();
+        self.current_stages = pipeline.get_stage_uniforms();
+
+        match self.current_stages.is_empty() {
+            true => {
+                self.current_program_pipeline = 0;
+                self.commands.push(Command::BindPipeline(pipeline.get() as GLuint));
+            }
+            false => {
+                self.current_program_pipeline = pipeline.get() as GLuint;
+                self.commands.push(Command::BindProgramPipeline(self.current_program_pipeline));
+            }
         }
 
-        // Keep track of the currently bound pipeline
-        self.current_pipeline = pipeline.get() as GLuint;
+        if let Some(count) = self.current_patch_vertices {
+            self.commands.push(Command::SetPatchVertices(count));
+        }
     }
 
     /// Set's the current active viewport
     fn set_viewport(&mut self, x: i32, y: i32, width: i32, height: i32){
-        unsafe {
-            gl::Viewport(x, y, width, height);
-        }
+        self.commands.push(Command::SetViewport { x, y, width, height });
     }
 
     /// Clears the current bound render target
     fn clear(&mut self, color: Option<Color>) {
-        unsafe {
-            match color {
-                Some(c) => gl::ClearColor(
-                    c.r as f32 / 255.0,
-                    c.g as f32 / 255.0,
-                    c.b as f32 / 255.0,
-                    c.a as f32 / 255.0,
-                ),
-                None => {}
-            }
-
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        }
+        self.commands.push(Command::Clear(color));
     }
 
     /// Binds a render target for drawing
     fn bind_rt(&mut self, rt: &RenderTarget) {
-        unsafe {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, rt.get_fb());
+        self.commands.push(Command::BindRenderTarget {
+            framebuffer: rt.get_fb(),
+        });
+    }
+
+    fn dispatch(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<(), Error> {
+        self.check_compute()?;
+        self.commands.push(Command::Dispatch { groups_x, groups_y, groups_z });
+
+        Ok(())
+    }
+
+    fn dispatch_indirect(&mut self, buffer: &impl buffers::BufferResource, offset: usize) -> Result<(), Error> {
+        self.check_compute()?;
+        Self::check_indirect(buffer, offset)?;
+        self.commands.push(Command::DispatchIndirect {
+            buffer: buffer.get_resource() as GLuint,
+            offset,
+        });
+
+        Ok(())
+    }
+
+    fn memory_barrier(&mut self, barrier: MemoryBarrier) {
+        self.commands.push(Command::MemoryBarrier(barrier));
+    }
+
+    fn draw(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology) -> Result<(), Error> {
+        self.check_graphics()?;
+        self.check_topology(topology)?;
+        self.commands.push(Command::Draw {
+            vertex_array: geometry.get_vertex_array(),
+            topology,
+            first: 0,
+            count: geometry.get_vertex_count(),
+        });
+
+        Ok(())
+    }
+
+    fn draw_indexed(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology) -> Result<(), Error> {
+        self.check_graphics()?;
+        self.check_topology(topology)?;
+        if geometry.get_index_buffer() == 0 {
+            return Err(Error::InvalidArgument("can not draw indexed geometry without an index buffer".to_string()));
         }
+
+        self.commands.push(Command::DrawIndexed {
+            vertex_array: geometry.get_vertex_array(),
+            index_buffer: geometry.get_index_buffer(),
+            topology,
+            count: geometry.get_index_count(),
+        });
+
+        Ok(())
     }
 
-    fn dispatch(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) {
-        unsafe {
-            gl::DispatchCompute(groups_x as GLuint, groups_y as GLuint, groups_z as GLuint);
+    fn draw_instanced(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology, instances: u32) -> Result<(), Error> {
+        self.check_graphics()?;
+        self.check_topology(topology)?;
+        let count = match geometry.get_index_buffer() {
+            0 => geometry.get_vertex_count(),
+            _ => geometry.get_index_count(),
+        };
+        self.commands.push(Command::DrawInstanced {
+            vertex_array: geometry.get_vertex_array(),
+            index_buffer: geometry.get_index_buffer(),
+            topology,
+            count,
+            instances,
+        });
+
+        Ok(())
+    }
+
+    fn draw_indirect(&mut self, geometry: &impl GeometryResource, topology: PrimitiveTopology, buffer: &impl buffers::BufferResource, offset: usize) -> Result<(), Error> {
+        self.check_graphics()?;
+        self.check_topology(topology)?;
+        Self::check_indirect(buffer, offset)?;
+        self.commands.push(Command::DrawIndirect {
+            vertex_array: geometry.get_vertex_array(),
+            index_buffer: geometry.get_index_buffer(),
+            topology,
+            buffer: buffer.get_resource() as GLuint,
+            offset,
+        });
+
+        Ok(())
+    }
+}
+
+impl GraphicsCommandList {
+    /// Only uniform, storage and atomic counter buffers have indexed binding points
+    fn indexed_usage(buffer: &impl buffers::BufferResource) -> Result<buffers::BufferUsage, Error> {
+        match buffer.get_usage() {
+            usage if usage.is_indexed() => Ok(usage),
+            usage => Err(Error::InvalidArgument(format!("a {:?} buffer can not be bound to a slot", usage))),
         }
     }
 
-    fn memory_barrier(&mut self, barrier: MemoryBarrier) {
-        unsafe { gl::MemoryBarrier(barrier.get()) }
+    fn check_compute(&self) -> Result<(), Error> {
+        match self.current_pipeline_type {
+            Some(PipelineType::Compute) => Ok(()),
+            _ => Err(Error::InvalidArgument("can not dispatch without a bound compute pipeline".to_string())),
+        }
+    }
+
+    fn check_graphics(&self) -> Result<(), Error> {
+        match self.current_pipeline_type {
+            Some(PipelineType::Graphics) => Ok(()),
+            _ => Err(Error::InvalidArgument("can not draw without a bound graphics pipeline".to_string())),
+        }
+    }
+
+    /// Indirect arguments are read from 4 byte aligned offsets of an indirect buffer, anything else is a GL_INVALID_VALUE when executed
+    fn check_indirect(buffer: &impl buffers::BufferResource, offset: usize) -> Result<(), Error> {
+        if buffer.get_usage() != buffers::BufferUsage::Indirect {
+            return Err(Error::InvalidArgument(format!("indirect arguments can not be read from a {:?} buffer", buffer.get_usage())));
+        }
+
+        match offset % 4 {
+            0 => Ok(()),
+            _ => Err(Error::InvalidArgument(format!("the indirect offset {} is not a multiple of 4", offset))),
+        }
+    }
+
+    /// Pipelines with tessellation stages can only draw patches and patches can only be drawn with them
+    fn check_topology(&self, topology: PrimitiveTopology) -> Result<(), Error> {
+        let tessellated = self.current_patch_vertices.is_some();
+
+        match (topology == PrimitiveTopology::Patches, tessellated) {
+            (true, false) => Err(Error::InvalidArgument("patches can only be drawn with a pipeline with tessellation stages".to_string())),
+            (false, true) => Err(Error::InvalidArgument("a pipeline with tessellation stages can only draw patches".to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod pipeline_builder_tests {
+    use super::*;
+
+    #[test]
+    fn build_without_shaders_is_invalid() {
+        let builder = PipelineBuilder::new();
+
+        match builder.build() {
+            Err(Error::InvalidStages(StageError::MissingVertexShader)) => {}
+            _ => panic!("Expected a missing vertex shader error"),
+        }
+
+        match builder.build_compute() {
+            Err(Error::InvalidStages(StageError::MissingComputeShader)) => {}
+            _ => panic!("Expected a missing compute shader error"),
+        }
+    }
+    #[test]
+    fn validates_stage_combinations() {
+        use ShaderType::*;
+
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (TessControl, TessControl), (TessEvaluation, TessEvaluation), (Geometry, Geometry), (Fragment, Fragment)], Some(4)), Ok(()));
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (TessEvaluation, TessEvaluation)], None), Ok(()));
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (TessControl, TessControl)], None), Err(StageError::MissingTessEvaluationShader));
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (Fragment, Fragment)], Some(3)), Err(StageError::PatchVerticesWithoutTessellation));
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (Geometry, Fragment)], None), Err(StageError::MismatchedStage { expected: Geometry, found: Fragment }));
+        assert_eq!(validate_graphics_stages(&[(Vertex, Vertex), (Compute, Compute)], None), Err(StageError::MixedComputeAndGraphics));
+        assert_eq!(validate_graphics_stages(&[(Compute, Compute)], None), Err(StageError::MixedComputeAndGraphics));
+
+        assert_eq!(validate_compute_stages(&[(Compute, Compute)]), Ok(()));
+        assert_eq!(validate_compute_stages(&[(Geometry, Geometry), (Compute, Compute)]), Err(StageError::MixedComputeAndGraphics));
+    }
+}
+
+#[cfg(test)]
+mod command_list_tests {
+    use super::*;
+    use buffers::BufferUsage;
+
+    // Fake resources, their ids are only recorded and never passed to OpenGL
+    struct TestPipeline {
+        id: GLuint,
+        pipeline_type: PipelineType,
+        patch_vertices: Option<u32>,
+        stages: Vec<StageUniforms>,
+    }
+
+    impl TestPipeline {
+        fn new(id: GLuint, pipeline_type: PipelineType) -> TestPipeline {
+            TestPipeline { id, pipeline_type, patch_vertices: None, stages: Vec::new() }
+        }
+
+        // A program pipeline whose fragment stage (program 5) declares "dt"
+        fn separable(id: GLuint) -> TestPipeline {
+            let stages = vec![
+                StageUniforms { stage: ShaderType::Vertex, program: 4, uniforms: Rc::default() },
+                StageUniforms { stage: ShaderType::Fragment, program: 5, uniforms: TestPipeline::uniforms() },
+            ];
+            TestPipeline { stages, ..TestPipeline::new(id, PipelineType::Graphics) }
+        }
+
+        fn tessellated(id: GLuint, patch_vertices: u32) -> TestPipeline {
+            TestPipeline { patch_vertices: Some(patch_vertices), ..TestPipeline::new(id, PipelineType::Graphics) }
+        }
+
+        // Every test pipeline declares a float "dt" at location 2
+        fn uniforms() -> Rc<UniformCache> {
+            let reflection = ProgramReflection {
+                uniforms: vec![
+                    reflection::UniformInfo { name: "dt".to_string(), data_type: reflection::DataType::Float, location: 2, array_size: 1 },
+                ],
+                ..Default::default()
+            };
+            Rc::new(UniformCache::new(&reflection))
+        }
+    }
+
+    impl Pipeline for TestPipeline {
+        fn get_type(self: &Self) -> PipelineType {
+            self.pipeline_type
+        }
+
+        fn get(self: &Self) -> *const std::ffi::c_void {
+            self.id as *const _
+        }
+
+        fn get_uniform_cache(self: &Self) -> Rc<UniformCache> {
+            TestPipeline::uniforms()
+        }
+
+        fn get_patch_vertices(self: &Self) -> Option<u32> {
+            self.patch_vertices
+        }
+
+        fn get_stage_uniforms(self: &Self) -> Vec<StageUniforms> {
+            self.stages.clone()
+        }
+    }
+
+    // A buffer of 4 byte elements
+    struct TestBuffer {
+        id: GLuint,
+        usage: BufferUsage,
+        size: usize,
+    }
+
+    impl TestBuffer {
+        fn new(id: GLuint, usage: BufferUsage, size: usize) -> TestBuffer {
+            TestBuffer { id, usage, size }
+        }
+    }
+
+    impl buffers::BufferResource for TestBuffer {
+        fn get_resource(self: &Self) -> *const std::ffi::c_void {
+            self.id as *const _
+        }
+
+        fn get_structure_size(self: &Self) -> usize {
+            4
+        }
+
+        fn get_buffer_size(self: &Self) -> usize {
+            self.size
+        }
+
+        fn get_usage(self: &Self) -> BufferUsage {
+            self.usage
+        }
+    }
+
+    struct TestGeometry {
+        ibo: GLuint,
+    }
+
+    impl GeometryResource for TestGeometry {
+        fn get_vertex_array(self: &Self) -> GLuint {
+            2
+        }
+
+        fn get_index_buffer(self: &Self) -> GLuint {
+            self.ibo
+        }
+
+        fn get_vertex_count(self: &Self) -> u32 {
+            4
+        }
+
+        fn get_index_count(self: &Self) -> u32 {
+            6
+        }
+    }
+
+    #[test]
+    fn binds_buffers_to_their_usage_target() {
+        let storage = TestBuffer::new(7, BufferUsage::Storage, 4);
+        let mut list = GraphicsCommandList::default();
+        list.bind_buffer(&storage, 1).unwrap();
+        list.bind_buffer(&TestBuffer::new(8, BufferUsage::Uniform, 16), 0).unwrap();
+        list.bind_buffer_range(&storage, 2, buffers::BufferRange { offset: 0, size: 4 }).unwrap();
+
+        assert_eq!(
+            list.commands(),
+            &[
+                Command::BindBuffer { buffer: 7, slot: 1 },
+                Command::BindBufferBase { target: gl::UNIFORM_BUFFER, buffer: 8, slot: 0 },
+                Command::BindBufferRange { target: gl::SHADER_STORAGE_BUFFER, buffer: 7, slot: 2, offset: 0, size: 4 },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_buffer_bindings_are_errors() {
+        let storage = TestBuffer::new(7, BufferUsage::Storage, 4);
+        let mut list = GraphicsCommandList::default();
+
+        assert!(matches!(list.bind_buffer(&TestBuffer::new(3, BufferUsage::Vertex, 36), 0), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.bind_buffer_range(&storage, 0, buffers::BufferRange { offset: 4, size: 4 }), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.bind_buffer_range(&storage, 0, buffers::BufferRange { offset: usize::MAX, size: 4 }), Err(Error::InvalidArgument(_))));
+        assert!(list.commands().is_empty());
+    }
+
+    #[test]
+    fn program_pipelines_set_uniforms_per_stage() {
+        let mut list = GraphicsCommandList::default();
+        list.set_strict(true);
+        list.bind_pipeline(&TestPipeline::separable(9));
+        list.set_uniform("dt", Uniform::Float(0.5)).unwrap();
+
+        assert!(matches!(list.set_stage_uniform(ShaderType::Vertex, "dt", Uniform::Float(0.5)), Err(Error::UnknownUniform(_))));
+        assert_eq!(
+            list.commands(),
+            &[
+                Command::BindProgramPipeline(9),
+                Command::SetActiveProgram { pipeline: 9, program: 5 },
+                Command::SetUniform { location: 2, uniform: Uniform::Float(0.5) },
+            ]
+        );
+    }
+
+    #[test]
+    fn records_commands_in_order() {
+        let mut list = GraphicsCommandList::default();
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Compute));
+        list.set_uniform("dt", Uniform::Float(0.5)).unwrap();
+        list.bind_buffer(&TestBuffer::new(7, BufferUsage::Storage, 4), 1).unwrap();
+        list.dispatch(8, 4, 1).unwrap();
+        list.memory_barrier(MemoryBarrier::ShaderStorage);
+
+        assert_eq!(
+            list.commands(),
+            &[
+                Command::BindPipeline(3),
+                Command::SetUniform { location: 2, uniform: Uniform::Float(0.5) },
+                Command::BindBuffer { buffer: 7, slot: 1 },
+                Command::Dispatch { groups_x: 8, groups_y: 4, groups_z: 1 },
+                Command::MemoryBarrier(MemoryBarrier::ShaderStorage),
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_instanced_uses_index_count_when_indexed() {
+        let mut list = GraphicsCommandList::default();
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Graphics));
+        list.draw_instanced(&TestGeometry { ibo: 5 }, PrimitiveTopology::Triangles, 10).unwrap();
+        list.draw_instanced(&TestGeometry { ibo: 0 }, PrimitiveTopology::TriangleStrip, 10).unwrap();
+
+        assert_eq!(
+            list.commands()[1..],
+            [
+                Command::DrawInstanced { vertex_array: 2, index_buffer: 5, topology: PrimitiveTopology::Triangles, count: 6, instances: 10 },
+                Command::DrawInstanced { vertex_array: 2, index_buffer: 0, topology: PrimitiveTopology::TriangleStrip, count: 4, instances: 10 },
+            ]
+        );
+    }
+
+    #[test]
+    fn patches_require_tessellation_stages() {
+        let mut list = GraphicsCommandList::default();
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Graphics));
+        assert!(matches!(list.draw(&TestGeometry { ibo: 0 }, PrimitiveTopology::Patches), Err(Error::InvalidArgument(_))));
+
+        list.bind_pipeline(&TestPipeline::tessellated(4, 3));
+        assert!(matches!(list.draw(&TestGeometry { ibo: 0 }, PrimitiveTopology::Triangles), Err(Error::InvalidArgument(_))));
+        list.draw(&TestGeometry { ibo: 0 }, PrimitiveTopology::Patches).unwrap();
+
+        assert_eq!(
+            list.commands()[1..],
+            [
+                Command::BindPipeline(4),
+                Command::SetPatchVertices(3),
+                Command::Draw { vertex_array: 2, topology: PrimitiveTopology::Patches, first: 0, count: 4 },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_dispatches_and_draws_are_errors() {
+        let indirect = TestBuffer::new(9, BufferUsage::Indirect, 32);
+        let mut list = GraphicsCommandList::default();
+        assert!(matches!(list.draw(&TestGeometry { ibo: 0 }, PrimitiveTopology::Triangles), Err(Error::InvalidArgument(_))));
+
+        list.bind_pipeline(&TestPipeline::new(1, PipelineType::Compute));
+        assert!(matches!(list.draw_instanced(&TestGeometry { ibo: 0 }, PrimitiveTopology::Triangles, 2), Err(Error::InvalidArgument(_))));
+
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Graphics));
+        assert!(matches!(list.dispatch(1, 1, 1), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.dispatch_indirect(&indirect, 0), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.draw_indexed(&TestGeometry { ibo: 0 }, PrimitiveTopology::Triangles), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.draw_indirect(&TestGeometry { ibo: 0 }, PrimitiveTopology::Triangles, &indirect, 2), Err(Error::InvalidArgument(_))));
+
+        list.bind_pipeline(&TestPipeline::new(1, PipelineType::Compute));
+        assert!(matches!(list.dispatch_indirect(&indirect, 6), Err(Error::InvalidArgument(_))));
+        assert!(matches!(list.dispatch_indirect(&TestBuffer::new(7, BufferUsage::Storage, 32), 0), Err(Error::InvalidArgument(_))));
+        assert_eq!(list.commands(), &[Command::BindPipeline(1), Command::BindPipeline(3), Command::BindPipeline(1)]);
+    }
+
+    #[test]
+    fn dispatch_indirect_records_buffer() {
+        let mut list = GraphicsCommandList::default();
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Compute));
+        list.dispatch_indirect(&TestBuffer::new(9, BufferUsage::Indirect, 32), 12).unwrap();
+
+        assert_eq!(list.commands()[1], Command::DispatchIndirect { buffer: 9, offset: 12 });
+    }
+
+    #[test]
+    fn unknown_uniforms_are_ignored_and_mismatches_set_unchecked() {
+        let mut list = GraphicsCommandList::default();
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Graphics));
+
+        assert!(list.set_uniform("missing", Uniform::Int(1)).is_ok());
+        assert!(list.set_uniform("dt", Uniform::Int(1)).is_ok());
+        assert_eq!(list.commands()[1..], [Command::SetUniform { location: 2, uniform: Uniform::Int(1) }]);
+    }
+
+    #[test]
+    fn strict_mode_reports_unknown_and_mismatched_uniforms() {
+        let mut list = GraphicsCommandList::default();
+        list.set_strict(true);
+        list.bind_pipeline(&TestPipeline::new(3, PipelineType::Graphics));
+
+        match list.set_uniform("missing", Uniform::Float(1.0)) {
+            Err(Error::UnknownUniform(name)) => assert_eq!(name, "missing"),
+            _ => panic!("Expected an unknown uniform error"),
+        }
+        match list.set_uniform("dt", Uniform::Int(1)) {
+            Err(Error::UniformTypeMismatch { location: 2, .. }) => {}
+            _ => panic!("Expected a type mismatch error"),
+        }
+        assert!(matches!(list.set_uniform("dt", Uniform::FloatArray(vec![1.0, 2.0])), Err(Error::InvalidArgument(_))));
+        assert_eq!(list.commands().len(), 1);
+    }
+
+    #[test]
+    fn reset_removes_commands() {
+        let mut list = GraphicsCommandList::default();
+        list.clear(Some(Color::new(0, 0, 0, 255)));
+        list.set_viewport(0, 0, 64, 64);
+        assert_eq!(list.commands().len(), 2);
+
+        list.reset();
+        assert!(list.commands().is_empty());
     }
 }
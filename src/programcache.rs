@@ -0,0 +1,180 @@
+use super::*;
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// Written in front of every cache entry, followed by the binary format and the program binary
+const ENTRY_MAGIC: &[u8; 4] = b"GLWB";
+
+/// Hashes bytes with 64-bit FNV-1a. Unlike ```std::hash``` the result is stable between runs and compiler versions,
+/// which is required for keys that are persisted to disk.
+pub(crate) fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Returns the hash of ```bytes``` starting from the FNV offset basis
+pub(crate) fn hash(bytes: &[u8]) -> u64 {
+    fnv1a(FNV_OFFSET, bytes)
+}
+
+/// Hit and miss counts of a ```ProgramCache```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    /// Programs loaded from the cache
+    pub hits: u32,
+    /// Programs that had to be linked, including rejected entries
+    pub misses: u32,
+    /// Entries that existed but were refused by the driver, e.g. after a driver update
+    pub rejected: u32,
+}
+
+/// # Program Cache
+/// Stores linked programs on disk with ```glGetProgramBinary```. Entries are keyed by a hash of the shader sources
+/// (which include the preprocessor defines) and the vendor, renderer and version strings of the driver.
+/// Pass the cache to ```PipelineBuilder::with_program_cache``` to use it.
+#[derive(Debug)]
+pub struct ProgramCache {
+    directory: PathBuf,
+    stats: Cell<CacheStats>,
+}
+
+impl ProgramCache {
+    /// Creates a cache that stores its entries in ```directory```, the directory is created when it does not exist
+    pub fn new<P: AsRef<Path>>(directory: P) -> Result<ProgramCache, Error> {
+        std::fs::create_dir_all(directory.as_ref())?;
+
+        Ok(ProgramCache {
+            directory: directory.as_ref().to_path_buf(),
+            stats: Cell::new(CacheStats::default()),
+        })
+    }
+
+    pub fn get_stats(&self) -> CacheStats {
+        self.stats.get()
+    }
+
+    /// Computes the key of a program built from ```shaders```, None when one of the shaders has no source to hash
+    pub(crate) fn key(&self, shaders: &[&Shader]) -> Option<u64> {
+        let mut key = hash(driver_string().as_bytes());
+        for shader in shaders {
+            key = fnv1a(key, &shader.get_type().value().to_le_bytes());
+            key = fnv1a(key, &shader.get_source_hash()?.to_le_bytes());
+        }
+
+        Some(key)
+    }
+
+    fn entry_path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", key))
+    }
+
+    /// Links ```program``` from the cache entry for ```key``` or, when there is no usable entry, from its
+    /// attached shaders. A freshly linked program is written to the cache.
+    pub(crate) fn link(&self, program: GLuint, key: u64, link: impl FnOnce(GLuint) -> Result<(), String>) -> Result<(), String> {
+        let mut stats = self.stats.get();
+
+        match self.load(program, key) {
+            Some(true) => {
+                stats.hits += 1;
+                self.stats.set(stats);
+                return Ok(());
+            }
+            Some(false) => stats.rejected += 1,
+            None => (),
+        }
+        stats.misses += 1;
+        self.stats.set(stats);
+
+        unsafe {
+            gl::ProgramParameteri(program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+        }
+        link(program)?;
+
+        // The cache is an optimization, failing to write an entry only means the next run links again
+        let _ = self.store(program, key);
+
+        Ok(())
+    }
+
+    /// Loads the binary of ```key``` into the program. Returns None when there is no entry and whether the driver accepted it otherwise.
+    fn load(&self, program: GLuint, key: u64) -> Option<bool> {
+        let entry = std::fs::read(self.entry_path(key)).ok()?;
+        if entry.len() < 8 || &entry[..4] != ENTRY_MAGIC {
+            return Some(false);
+        }
+        let format = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+        let binary = &entry[8..];
+
+        unsafe {
+            gl::ProgramBinary(program, format, binary.as_ptr() as *const c_void, binary.len() as GLsizei);
+
+            let mut success: GLint = 0;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+            Some(success != 0)
+        }
+    }
+
+    fn store(&self, program: GLuint, key: u64) -> Result<(), Error> {
+        let mut entry = ENTRY_MAGIC.to_vec();
+
+        unsafe {
+            let mut length: GLint = 0;
+            gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length);
+            if length <= 0 {
+                return Err(Error::Unsupported("the driver did not return a program binary".to_string()));
+            }
+
+            let mut binary: Vec<u8> = vec![0; length as usize];
+            let mut format: GLenum = 0;
+            let mut written: GLsizei = 0;
+            gl::GetProgramBinary(program, length, &mut written, &mut format, binary.as_mut_ptr() as *mut c_void);
+            binary.truncate(written.max(0) as usize);
+
+            entry.extend_from_slice(&format.to_le_bytes());
+            entry.extend_from_slice(&binary);
+        }
+
+        std::fs::write(self.entry_path(key), entry)?;
+        Ok(())
+    }
+}
+
+/// Identifies the driver, program binaries are only valid for the driver that produced them
+fn driver_string() -> String {
+    [gl::VENDOR, gl::RENDERER, gl::VERSION]
+        .iter()
+        .map(|name| unsafe {
+            let value = gl::GetString(*name);
+            match value.is_null() {
+                true => String::new(),
+                false => CStr::from_ptr(value as *const GLchar).to_string_lossy().into_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod program_cache_tests {
+    use super::*;
+
+    #[test]
+    fn fnv_hash_is_stable() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(hash(b"foo"), b"bar"), hash(b"foobar"));
+    }
+
+    #[test]
+    fn entries_are_named_by_key() {
+        let dir = std::env::temp_dir().join(format!("glw_{}", std::process::id())).join("program_cache");
+        let cache = ProgramCache::new(&dir).unwrap();
+
+        assert!(dir.is_dir());
+        assert_eq!(cache.entry_path(0xff), dir.join("00000000000000ff.bin"));
+        assert_eq!(cache.get_stats(), CacheStats::default());
+    }
+}
@@ -0,0 +1,420 @@
+use super::*;
+
+use std::collections::HashMap;
+
+/// GLSL data types reported by program interface queries
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    BVec2,
+    BVec3,
+    BVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler2DArray,
+    Image2D,
+    Image3D,
+    ImageCube,
+    Image2DArray,
+    Unknown(GLenum),
+}
+
+impl DataType {
+    pub fn from_gl(value: GLenum) -> DataType {
+        match value {
+            gl::FLOAT => DataType::Float,
+            gl::FLOAT_VEC2 => DataType::Vec2,
+            gl::FLOAT_VEC3 => DataType::Vec3,
+            gl::FLOAT_VEC4 => DataType::Vec4,
+            gl::INT => DataType::Int,
+            gl::INT_VEC2 => DataType::IVec2,
+            gl::INT_VEC3 => DataType::IVec3,
+            gl::INT_VEC4 => DataType::IVec4,
+            gl::UNSIGNED_INT => DataType::UInt,
+            gl::UNSIGNED_INT_VEC2 => DataType::UVec2,
+            gl::UNSIGNED_INT_VEC3 => DataType::UVec3,
+            gl::UNSIGNED_INT_VEC4 => DataType::UVec4,
+            gl::BOOL => DataType::Bool,
+            gl::BOOL_VEC2 => DataType::BVec2,
+            gl::BOOL_VEC3 => DataType::BVec3,
+            gl::BOOL_VEC4 => DataType::BVec4,
+            gl::FLOAT_MAT2 => DataType::Mat2,
+            gl::FLOAT_MAT3 => DataType::Mat3,
+            gl::FLOAT_MAT4 => DataType::Mat4,
+            gl::SAMPLER_2D => DataType::Sampler2D,
+            gl::SAMPLER_3D => DataType::Sampler3D,
+            gl::SAMPLER_CUBE => DataType::SamplerCube,
+            gl::SAMPLER_2D_ARRAY => DataType::Sampler2DArray,
+            gl::IMAGE_2D => DataType::Image2D,
+            gl::IMAGE_3D => DataType::Image3D,
+            gl::IMAGE_CUBE => DataType::ImageCube,
+            gl::IMAGE_2D_ARRAY => DataType::Image2DArray,
+            _ => DataType::Unknown(value),
+        }
+    }
+}
+
+/// An active uniform that is not part of a uniform block
+#[derive(Clone, Debug, PartialEq)]
+pub struct UniformInfo {
+    pub name: String,
+    pub data_type: DataType,
+    pub location: i32,
+    pub array_size: i32,
+}
+
+/// A member of a uniform or shader storage block
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockMember {
+    pub name: String,
+    pub data_type: DataType,
+    /// Byte offset from the start of the block
+    pub offset: i32,
+    pub array_size: i32,
+    pub array_stride: i32,
+    pub matrix_stride: i32,
+}
+
+/// An active uniform block or shader storage block
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockInfo {
+    pub name: String,
+    /// Binding point the block reads from, as used by ```bind_buffer```
+    pub binding: u32,
+    /// Minimum size in bytes of a buffer bound to this block
+    pub data_size: i32,
+    pub members: Vec<BlockMember>,
+}
+
+impl BlockInfo {
+    /// Checks that the block can be backed by a buffer of ```T```s, such as a ```StructuredBuffer<T>```.
+    /// Blocks ending in an unsized array need to match the array stride, other blocks the size of the whole block.
+    pub fn check_element_size<T>(&self) -> Result<(), Error> {
+        let (what, expected) = match self.members.last() {
+            Some(member) if member.array_size == 0 => ("array stride", member.array_stride),
+            _ => ("size", self.data_size),
+        };
+
+        match expected as usize == std::mem::size_of::<T>() {
+            true => Ok(()),
+            false => Err(Error::InvalidArgument(format!(
+                "block \"{}\" has a {} of {} bytes but {} is {} bytes",
+                self.name, what, expected, std::any::type_name::<T>(), std::mem::size_of::<T>()))),
+        }
+    }
+}
+
+/// An active vertex shader input
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexInput {
+    pub name: String,
+    pub data_type: DataType,
+    pub location: i32,
+    pub array_size: i32,
+}
+
+/// # Program Reflection
+/// Describes the interface of a linked program: the uniforms, blocks and vertex inputs it expects.
+/// Created through ```reflect()``` on a pipeline.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramReflection {
+    pub uniforms: Vec<UniformInfo>,
+    pub uniform_blocks: Vec<BlockInfo>,
+    pub storage_blocks: Vec<BlockInfo>,
+    pub inputs: Vec<VertexInput>,
+}
+
+impl ProgramReflection {
+    /// Queries the active resources of a linked program
+    pub fn query(program: GLuint) -> ProgramReflection {
+        let uniforms = (0..resource_count(program, gl::UNIFORM))
+            .filter_map(|index| {
+                let [name_length, data_type, location, array_size, block_index] = resource_properties(
+                    program,
+                    gl::UNIFORM,
+                    index,
+                    [gl::NAME_LENGTH, gl::TYPE, gl::LOCATION, gl::ARRAY_SIZE, gl::BLOCK_INDEX],
+                );
+
+                // Members of uniform blocks are reported with their block
+                match block_index {
+                    -1 => Some(UniformInfo {
+                        name: resource_name(program, gl::UNIFORM, index, name_length),
+                        data_type: DataType::from_gl(data_type as GLenum),
+                        location,
+                        array_size,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let inputs = (0..resource_count(program, gl::PROGRAM_INPUT))
+            .map(|index| {
+                let [name_length, data_type, location, array_size] = resource_properties(
+                    program,
+                    gl::PROGRAM_INPUT,
+                    index,
+                    [gl::NAME_LENGTH, gl::TYPE, gl::LOCATION, gl::ARRAY_SIZE],
+                );
+
+                VertexInput {
+                    name: resource_name(program, gl::PROGRAM_INPUT, index, name_length),
+                    data_type: DataType::from_gl(data_type as GLenum),
+                    location,
+                    array_size,
+                }
+            })
+            // Built-in inputs like gl_VertexID have no location
+            .filter(|input| input.location != -1)
+            .collect();
+
+        ProgramReflection {
+            uniforms,
+            uniform_blocks: query_blocks(program, gl::UNIFORM_BLOCK, gl::UNIFORM),
+            storage_blocks: query_blocks(program, gl::SHADER_STORAGE_BLOCK, gl::BUFFER_VARIABLE),
+            inputs,
+        }
+    }
+
+    pub fn get_uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.iter().find(|u| u.name == name)
+    }
+
+    pub fn get_uniform_block(&self, name: &str) -> Option<&BlockInfo> {
+        self.uniform_blocks.iter().find(|b| b.name == name)
+    }
+
+    pub fn get_storage_block(&self, name: &str) -> Option<&BlockInfo> {
+        self.storage_blocks.iter().find(|b| b.name == name)
+    }
+
+    /// Returns the shader storage block read from the binding slot passed to ```bind_buffer```
+    pub fn get_storage_block_at(&self, binding: u32) -> Option<&BlockInfo> {
+        self.storage_blocks.iter().find(|b| b.binding == binding)
+    }
+
+    pub fn get_input(&self, name: &str) -> Option<&VertexInput> {
+        self.inputs.iter().find(|i| i.name == name)
+    }
+}
+
+fn resource_count(program: GLuint, interface: GLenum) -> u32 {
+    let mut count: GLint = 0;
+    unsafe {
+        gl::GetProgramInterfaceiv(program, interface, gl::ACTIVE_RESOURCES, &mut count);
+    }
+    count.max(0) as u32
+}
+
+fn resource_properties<const N: usize>(program: GLuint, interface: GLenum, index: u32, properties: [GLenum; N]) -> [GLint; N] {
+    let mut values: [GLint; N] = [0; N];
+    unsafe {
+        gl::GetProgramResourceiv(
+            program,
+            interface,
+            index,
+            N as GLsizei,
+            properties.as_ptr(),
+            N as GLsizei,
+            std::ptr::null_mut(),
+            values.as_mut_ptr(),
+        );
+    }
+    values
+}
+
+fn resource_name(program: GLuint, interface: GLenum, index: u32, name_length: GLint) -> String {
+    let mut name: Vec<u8> = vec![0; name_length.max(1) as usize];
+    let mut length: GLsizei = 0;
+    unsafe {
+        gl::GetProgramResourceName(
+            program,
+            interface,
+            index,
+            name.len() as GLsizei,
+            &mut length,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+    }
+    name.truncate(length.max(0) as usize);
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+/// Queries all blocks of ```block_interface``` with their members from ```member_interface```
+fn query_blocks(program: GLuint, block_interface: GLenum, member_interface: GLenum) -> Vec<BlockInfo> {
+    (0..resource_count(program, block_interface))
+        .map(|index| {
+            let [name_length, binding, data_size, member_count] = resource_properties(
+                program,
+                block_interface,
+                index,
+                [gl::NAME_LENGTH, gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE, gl::NUM_ACTIVE_VARIABLES],
+            );
+
+            let mut member_indices: Vec<GLint> = vec![0; member_count.max(0) as usize];
+            if !member_indices.is_empty() {
+                let property = gl::ACTIVE_VARIABLES;
+                unsafe {
+                    gl::GetProgramResourceiv(
+                        program,
+                        block_interface,
+                        index,
+                        1,
+                        &property,
+                        member_indices.len() as GLsizei,
+                        std::ptr::null_mut(),
+                        member_indices.as_mut_ptr(),
+                    );
+                }
+            }
+
+            let mut members: Vec<BlockMember> = member_indices
+                .iter()
+                .map(|&member| {
+                    let member = member as u32;
+                    let [name_length, data_type, offset, array_size, array_stride, matrix_stride] = resource_properties(
+                        program,
+                        member_interface,
+                        member,
+                        [gl::NAME_LENGTH, gl::TYPE, gl::OFFSET, gl::ARRAY_SIZE, gl::ARRAY_STRIDE, gl::MATRIX_STRIDE],
+                    );
+
+                    BlockMember {
+                        name: resource_name(program, member_interface, member, name_length),
+                        data_type: DataType::from_gl(data_type as GLenum),
+                        offset,
+                        array_size,
+                        array_stride,
+                        matrix_stride,
+                    }
+                })
+                .collect();
+            members.sort_by_key(|m| m.offset);
+
+            BlockInfo {
+                name: resource_name(program, block_interface, index, name_length),
+                binding: binding as u32,
+                data_size,
+                members,
+            }
+        })
+        .collect()
+}
+
+/// # Uniform Handle
+/// Location and type of a uniform in a linked program. Setting uniforms through a handle skips the name lookup.
+/// A handle is only valid for the pipeline it was retrieved from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UniformHandle {
+    location: i32,
+    data_type: DataType,
+    // Array elements from the location to the end of the array, 1 for uniforms that are not arrays
+    array_size: i32,
+}
+
+impl UniformHandle {
+    pub fn get_location(&self) -> i32 {
+        self.location
+    }
+
+    pub fn get_type(&self) -> DataType {
+        self.data_type
+    }
+
+    /// Returns how many array elements can be set through the handle
+    pub fn get_array_size(&self) -> i32 {
+        self.array_size
+    }
+}
+
+/// # Uniform Cache
+/// Uniform locations of a linked program keyed by name, built once when the pipeline is linked.
+/// Array elements can be looked up as ```name[i]```.
+#[derive(Clone, Debug, Default)]
+pub struct UniformCache {
+    // Handle per uniform name
+    uniforms: HashMap<String, UniformHandle>,
+}
+
+impl UniformCache {
+    pub fn new(reflection: &ProgramReflection) -> UniformCache {
+        let mut uniforms = HashMap::new();
+        for uniform in &reflection.uniforms {
+            let entry = UniformHandle { location: uniform.location, data_type: uniform.data_type, array_size: uniform.array_size };
+
+            // Arrays are reported as "name[0]", make them available without the subscript as well
+            if let Some(base) = uniform.name.strip_suffix("[0]") {
+                uniforms.insert(base.to_string(), entry);
+            }
+            uniforms.insert(uniform.name.clone(), entry);
+        }
+
+        UniformCache { uniforms }
+    }
+
+    /// Returns the handle of an active uniform, ```None``` when the program does not use it
+    pub fn get(&self, name: &str) -> Option<UniformHandle> {
+        if let Some(handle) = self.uniforms.get(name) {
+            return Some(*handle);
+        }
+
+        // Resolve "name[i]" relative to the first element of the array
+        let (base, index) = name.strip_suffix(']')?.split_once('[')?;
+        let index: i32 = index.parse().ok()?;
+        let handle = self.uniforms.get(base)?;
+        match index < handle.array_size {
+            true => Some(UniformHandle { location: handle.location + index, data_type: handle.data_type, array_size: handle.array_size - index }),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod uniform_cache_tests {
+    use super::*;
+
+    fn reflection() -> ProgramReflection {
+        ProgramReflection {
+            uniforms: vec![
+                UniformInfo { name: "dt".to_string(), data_type: DataType::Float, location: 0, array_size: 1 },
+                UniformInfo { name: "weights[0]".to_string(), data_type: DataType::Vec2, location: 4, array_size: 3 },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_uniforms_by_name() {
+        let cache = UniformCache::new(&reflection());
+
+        assert_eq!(cache.get("dt").map(|h| h.get_location()), Some(0));
+        assert_eq!(cache.get("weights").map(|h| h.get_location()), Some(4));
+        assert_eq!(cache.get("missing"), None);
+    }
+
+    #[test]
+    fn resolves_array_elements() {
+        let cache = UniformCache::new(&reflection());
+
+        assert_eq!(cache.get("weights[2]").map(|h| h.get_location()), Some(6));
+        assert_eq!(cache.get("weights[2]").map(|h| h.get_array_size()), Some(1));
+        assert_eq!(cache.get("weights[3]"), None);
+    }
+}
@@ -71,7 +71,7 @@ impl Default for RenderTarget{
 impl RenderTarget{
 
     /// Creates a new render target with a specified size
-    pub fn new(size : Vec2<u32>) -> Result<RenderTarget, &'static str> {
+    pub fn new(size : Vec2<u32>) -> Result<RenderTarget, Error> {
         let mut tex = 0;
         let mut fb = 0;
         let fmt = PixelFormat::RGBA32F;
@@ -103,7 +103,10 @@ impl RenderTarget{
             gl::BindTexture(gl::TEXTURE_2D,0);
 
             if complete != gl::FRAMEBUFFER_COMPLETE {
-                return Err("Render target creation failed. The framebuffer was not complete.");
+                gl::DeleteFramebuffers(1, &fb);
+                gl::DeleteTextures(1, &tex);
+
+                return Err(Error::IncompleteFramebuffer(error::FramebufferStatus::from_gl(complete)));
             }
         }
 
@@ -0,0 +1,188 @@
+use super::*;
+
+use std::rc::Rc;
+
+use super::program::{Pipeline, PipelineType, StageError, StageUniforms};
+
+/// # Separable Program
+/// A program linked with ```GL_PROGRAM_SEPARABLE``` from a single shader stage.
+/// Separable programs of different stages are combined into a ```ProgramPipeline``` without relinking,
+/// so one program can be shared by many pipelines.
+pub struct SeparableProgram {
+    // Open GL program ID
+    id: GLuint,
+    stage: ShaderType,
+
+    // Interface of the program, queried at link time
+    reflection: ProgramReflection,
+    uniforms: Rc<UniformCache>,
+}
+
+impl SeparableProgram {
+    pub fn new(shader: &Shader) -> Result<SeparableProgram, Error> {
+        let mut result = unsafe {
+            SeparableProgram {
+                id: gl::CreateProgram(),
+                stage: shader.get_type(),
+                reflection: ProgramReflection::default(),
+                uniforms: Rc::default(),
+            }
+        };
+
+        unsafe {
+            gl::ProgramParameteri(result.id, gl::PROGRAM_SEPARABLE, gl::TRUE as GLint);
+            gl::AttachShader(result.id, shader.get_id());
+        }
+
+        program::link_program(result.id).map_err(|log| Error::ProgramLink { log, stages: vec![result.stage] })?;
+
+        unsafe {
+            // The shader is no longer needed once the program is linked
+            gl::DetachShader(result.id, shader.get_id());
+        }
+
+        result.reflection = ProgramReflection::query(result.id);
+        result.uniforms = Rc::new(UniformCache::new(&result.reflection));
+
+        Ok(result)
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn get_stage(&self) -> ShaderType {
+        self.stage
+    }
+
+    /// Returns the active uniforms and blocks of the linked program
+    pub fn reflect(&self) -> &ProgramReflection {
+        &self.reflection
+    }
+
+    /// Returns the program info log of the last link, see ```GraphicsPipeline::get_link_log```
+    pub fn get_link_log(&self) -> String {
+        program::program_info_log(self.id)
+    }
+
+    /// Returns a handle to an active uniform of this stage
+    pub fn get_uniform_handle(&self, name: &str) -> Option<UniformHandle> {
+        self.uniforms.get(name)
+    }
+}
+
+impl Drop for SeparableProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
+
+/// # Program Pipeline
+/// Combines separable programs, one per stage, into a pipeline object that can be bound to a command list.
+/// Uniforms are set per stage with ```CommandList::set_stage_uniform```, ```set_uniform``` sets the uniform
+/// on the first stage that declares it.
+pub struct ProgramPipeline {
+    // Open GL program pipeline ID
+    id: GLuint,
+    programs: Vec<Rc<SeparableProgram>>,
+
+    // Vertices per patch, only set for pipelines with tessellation stages
+    patch_vertices: Option<u32>,
+}
+
+impl ProgramPipeline {
+    /// Creates a pipeline from separable programs. The stages are validated like in ```PipelineBuilder```.
+    pub fn new(programs: &[Rc<SeparableProgram>]) -> Result<ProgramPipeline, Error> {
+        let stages: Vec<(ShaderType, ShaderType)> = programs.iter().map(|p| (p.stage, p.stage)).collect();
+        for (i, (stage, _)) in stages.iter().enumerate() {
+            if stages[..i].iter().any(|(other, _)| other == stage) {
+                return Err(Error::InvalidStages(StageError::DuplicateStage(*stage)));
+            }
+        }
+        match stages.iter().any(|(stage, _)| *stage == ShaderType::Compute) {
+            true => program::validate_compute_stages(&stages),
+            false => program::validate_graphics_stages(&stages, None),
+        }
+        .map_err(Error::InvalidStages)?;
+
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::CreateProgramPipelines(1, &mut id);
+            for program in programs {
+                gl::UseProgramStages(id, program.stage.stage_bit(), program.id);
+            }
+        }
+
+        let tessellated = stages.iter().any(|(stage, _)| *stage == ShaderType::TessEvaluation);
+        Ok(ProgramPipeline {
+            id,
+            programs: programs.to_vec(),
+            patch_vertices: match tessellated {
+                true => Some(3),
+                false => None,
+            },
+        })
+    }
+
+    /// Sets the number of vertices per patch used when drawing with the pipeline, defaults to 3.
+    /// Requires a tessellation evaluation stage.
+    pub fn set_patch_vertices(&mut self, count: u32) -> Result<(), Error> {
+        if self.patch_vertices.is_none() {
+            return Err(Error::InvalidStages(StageError::PatchVerticesWithoutTessellation));
+        }
+        if count == 0 {
+            return Err(Error::InvalidArgument("a patch needs at least one vertex".to_string()));
+        }
+        self.patch_vertices = Some(count);
+
+        Ok(())
+    }
+
+    /// Returns the program bound to ```stage```
+    pub fn get_program(&self, stage: ShaderType) -> Option<&Rc<SeparableProgram>> {
+        self.programs.iter().find(|p| p.stage == stage)
+    }
+}
+
+impl Pipeline for ProgramPipeline {
+    fn get_type(self: &Self) -> PipelineType {
+        match self.get_program(ShaderType::Compute) {
+            Some(_) => PipelineType::Compute,
+            None => PipelineType::Graphics,
+        }
+    }
+
+    fn get(self: &Self) -> *const std::ffi::c_void {
+        self.id as *const _
+    }
+
+    /// Program pipelines have no uniforms of their own, they are set on the programs of the stages
+    fn get_uniform_cache(self: &Self) -> Rc<UniformCache> {
+        Rc::default()
+    }
+
+    fn get_patch_vertices(self: &Self) -> Option<u32> {
+        self.patch_vertices
+    }
+
+    fn get_stage_uniforms(self: &Self) -> Vec<StageUniforms> {
+        self.programs
+            .iter()
+            .map(|p| StageUniforms {
+                stage: p.stage,
+                program: p.id,
+                uniforms: p.uniforms.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgramPipelines(1, &self.id);
+        }
+    }
+}
@@ -1,30 +1,57 @@
 use super::*;
 use std::ffi::CString;
-use std::fs::File;
-use std::io::Read;
-use std::error::Error;
+use std::path::PathBuf;
 
+use super::hotreload::FileWatch;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ShaderType
 {
     Vertex   ,
     Fragment,
     Compute ,
-    Geometry 
+    Geometry ,
+    TessControl,
+    TessEvaluation
 }
 impl ShaderType{
-    fn value(&self) -> GLenum{
+    pub(crate) fn value(&self) -> GLenum{
         match *self {
             ShaderType::Vertex => gl::VERTEX_SHADER,
             ShaderType::Fragment => gl::FRAGMENT_SHADER,
             ShaderType::Compute => gl::COMPUTE_SHADER,
-            ShaderType::Geometry => gl::GEOMETRY_SHADER
+            ShaderType::Geometry => gl::GEOMETRY_SHADER,
+            ShaderType::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderType::TessEvaluation => gl::TESS_EVALUATION_SHADER
+        }
+    }
+
+    /// Returns the bit used for the stage in glUseProgramStages
+    pub(crate) fn stage_bit(&self) -> GLbitfield {
+        match *self {
+            ShaderType::Vertex => gl::VERTEX_SHADER_BIT,
+            ShaderType::Fragment => gl::FRAGMENT_SHADER_BIT,
+            ShaderType::Compute => gl::COMPUTE_SHADER_BIT,
+            ShaderType::Geometry => gl::GEOMETRY_SHADER_BIT,
+            ShaderType::TessControl => gl::TESS_CONTROL_SHADER_BIT,
+            ShaderType::TessEvaluation => gl::TESS_EVALUATION_SHADER_BIT
         }
     }
 }
 /// A wrapper around opengl shader objects. 
 pub struct Shader {
     id: u32,
-    _shader_type: ShaderType
+    shader_type: ShaderType,
+
+    // Preprocessed source when loaded from a file, used to map compiler messages back to the original files
+    source: Option<PreprocessedSource>,
+
+    // File and preprocessor the shader was loaded with, used to recompile it when the file changes
+    origin: Option<(PathBuf, Preprocessor)>,
+    watch: FileWatch,
+
+    // Hash of the compiled source or binary, used as key for the program cache
+    source_hash: Option<u64>,
 }
 
 impl Drop for Shader {
@@ -41,83 +68,433 @@ impl Shader {
     pub fn get_id(&self) -> GLuint {
         self.id
     }
+
+    /// Returns the pipeline stage this shader was created for
+    pub fn get_type(&self) -> ShaderType {
+        self.shader_type
+    }
+
     // Creates a new empty shader object
     pub fn new(shader_type: ShaderType) -> Shader {
         unsafe {
             Shader {
                 id: gl::CreateShader(shader_type.value()),
-                _shader_type: shader_type
+                shader_type,
+                source: None,
+                origin: None,
+                watch: FileWatch::default(),
+                source_hash: None,
             }
         }
     }
 
     /// Loads and compiles a shader from memory.
-    pub fn load_from_memory(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+    pub fn load_from_memory(&mut self, data: &str) -> Result<(), Error> {
         unsafe {
             let shader_id = self.id;
 
             let c_string = CString::new(data)?;
             gl::ShaderSource(shader_id, 1, &c_string.as_ptr(), ::std::ptr::null());
             gl::CompileShader(shader_id);
+        }
+        self.source_hash = Some(programcache::hash(data.as_bytes()));
+
+        self.check_compile_status()
+    }
 
+    /// Loads a SPIR-V module and specializes it with the entry point and constants of ```specialization```.
+    /// Requires OpenGL 4.6 or ARB_gl_spirv, ```Error::Unsupported``` is returned otherwise.
+    pub fn load_from_spirv(&mut self, binary: &[u8], specialization: &spirv::Specialization) -> Result<(), Error> {
+        spirv::specialize(self.id, binary, specialization)?;
+        self.source_hash = Some(spirv::hash(binary, specialization));
+
+        self.check_compile_status()
+    }
+
+    /// Loads a SPIR-V module from a file on disk, see ```load_from_spirv```
+    pub fn load_spirv_file(&mut self, path: &str, specialization: &spirv::Specialization) -> Result<(), Error> {
+        let binary = std::fs::read(path)?;
+
+        self.load_from_spirv(&binary, specialization)
+    }
+
+    /// Returns the info log as error when the last compilation or specialization failed
+    fn check_compile_status(&self) -> Result<(), Error> {
+        unsafe {
             let mut success: GLint = 0;
-            gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut success);
+            gl::GetShaderiv(self.id, gl::COMPILE_STATUS, &mut success);
 
             match success {
                 0 => {
-                    let mut log_size: GLint = 0;
-                    gl::GetShaderiv(shader_id, gl::INFO_LOG_LENGTH, &mut log_size);
-                    let mut msg: Vec<u8> = Vec::new();
-                    msg.resize(log_size as usize, 0);
-
-                    let mut new_length = 0;
-                    gl::GetShaderInfoLog(
-                        shader_id,
-                        log_size,
-                        &mut new_length,
-                        msg.as_ptr() as *mut GLchar,
-                    );
-
-                    let msg = format!(
-                        "Failed to compile shader : {}",
-                        String::from_utf8(msg).unwrap()
-                    );
-
-                    Err(From::from(msg))
+                    let log = self.get_info_log();
+                    Err(Error::ShaderCompile {
+                        stage: self.shader_type,
+                        diagnostics: Diagnostic::parse_log(&log),
+                        log,
+                    })
                 }
                 _ => Ok(()), // Return empty OK
             }
         }
     }
 
-    /// Loads and compiles a shader from a file on disk.
-    pub fn load_from_file(&mut self, path: &str) -> Result<(), Box<dyn Error> > {
-        let mut file = File::open(&path)?;
-        let mut content = String::new();
+    /// Returns the info log of the last compilation, drivers also write warnings to it when compilation succeeds
+    pub fn get_info_log(&self) -> String {
+        unsafe {
+            let mut log_size: GLint = 0;
+            gl::GetShaderiv(self.id, gl::INFO_LOG_LENGTH, &mut log_size);
+            let mut msg: Vec<u8> = vec![0; log_size.max(0) as usize];
+
+            let mut new_length = 0;
+            gl::GetShaderInfoLog(self.id, log_size, &mut new_length, msg.as_mut_ptr() as *mut GLchar);
+            msg.truncate(new_length.max(0) as usize);
+
+            String::from_utf8_lossy(&msg).into_owned()
+        }
+    }
+
+    /// Returns the diagnostics of a successful compilation, e.g. warnings.
+    /// Locations refer to the original files for shaders loaded with ```load_from_file```.
+    pub fn get_warnings(&self) -> Vec<Diagnostic> {
+        let diagnostics = Diagnostic::parse_log(&self.get_info_log());
+
+        match &self.source {
+            Some(source) => diagnostics.iter().map(|d| source.map_diagnostic(d)).collect(),
+            None => diagnostics,
+        }
+    }
+
+    /// Loads and compiles a shader from a file on disk. Includes are resolved relative to the file.
+    pub fn load_from_file(&mut self, path: &str) -> Result<(), Error> {
+        self.load_from_file_with(path, &Preprocessor::default())
+    }
+
+    /// Loads a shader from a file on disk, runs it through the preprocessor and compiles it.
+    /// Line numbers and file indices in compile errors refer to the original files,
+    /// use ```PreprocessedSource::pretty_print``` to format the diagnostics.
+    pub fn load_from_file_with(&mut self, path: &str, preprocessor: &Preprocessor) -> Result<(), Error> {
+        self.origin = Some((PathBuf::from(path), preprocessor.clone()));
+        self.watch = FileWatch::new(vec![PathBuf::from(path)]);
+
+        let source = preprocessor.process_file(path)?;
+        self.watch = FileWatch::new(source.files.iter().cloned());
+
+        let result = match self.load_from_memory(&source.source) {
+            Err(Error::ShaderCompile { stage, log, diagnostics }) => Err(Error::ShaderCompile {
+                stage,
+                log: source.map_log(&log),
+                diagnostics: diagnostics.iter().map(|d| source.map_diagnostic(d)).collect(),
+            }),
+            result => result,
+        };
+
+        self.source = Some(source);
+        result
+    }
+
+    /// Returns the preprocessed source of shaders loaded from a file
+    pub fn get_source(&self) -> Option<&PreprocessedSource> {
+        self.source.as_ref()
+    }
+
+    pub(crate) fn get_source_hash(&self) -> Option<u64> {
+        self.source_hash
+    }
+
+    /// Compiles a new shader from the file this shader was loaded from.
+    /// Returns None for shaders that were not loaded with ```load_from_file```.
+    pub fn recompile(&self) -> Option<Result<Shader, Error>> {
+        let (path, preprocessor) = self.origin.as_ref()?;
 
-        file.read_to_string(&mut content)
-            .expect("Failed to read from file");
+        let mut shader = Shader::new(self.shader_type);
+        Some(shader
+            .load_from_file_with(&path.to_string_lossy(), preprocessor)
+            .map(|_| shader))
+    }
 
-        let c_content: CString = CString::new(content.as_bytes()).unwrap();
+    /// Recompiles the shader in place when one of its source files changed since it was loaded or last reloaded.
+    /// Returns whether the shader was recompiled. When compilation fails the previously compiled shader is kept.
+    /// Pipelines using the shader need to be relinked to pick up the change, see ```PipelineBuilder::with_hot_reload```.
+    pub fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        if !self.watch.poll() {
+            return Ok(false);
+        }
 
-        match c_content.to_str() {
-            Ok(v) => { 
-                self.load_from_memory(v)
-            },
-            Err(err) => {
-                Err(From::from(format!("Failed to load shader for path \"{}\": \n{}", path, err))) 
+        match self.recompile() {
+            Some(Ok(mut shader)) => {
+                // Swap the new shader object in, the old one is deleted when `shader` is dropped
+                std::mem::swap(&mut self.id, &mut shader.id);
+                std::mem::swap(&mut self.source, &mut shader.source);
+                std::mem::swap(&mut self.watch, &mut shader.watch);
+                std::mem::swap(&mut self.source_hash, &mut shader.source_hash);
+                Ok(true)
             }
+            Some(Err(err)) => Err(err),
+            None => Ok(false),
         }
     }
 }
 
 
 /// Enum that carries data for specific Uniforms in GLSL. 
-/// It describes what data to bind for the uniform retrieved with glGetUniformLocation
+/// It describes what data to bind for the uniform retrieved with glGetUniformLocation.
+/// Sampler variants bind the texture with the given id to the texture unit with the same index,
+/// image variants only set the image unit, the texture is bound to that unit with ```bind_texture```.
+/// Matrices are column major, the ```bool``` of matrix variants transposes them on upload.
 #[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Uniform {
     Float(f32),
     Int(i32),
     Vec2(f32,f32),
     Sampler2D(GLuint),
+    Vec3(f32,f32,f32),
+    Vec4(f32,f32,f32,f32),
+    UInt(u32),
+    Bool(bool),
+    IVec2(i32,i32),
+    IVec3(i32,i32,i32),
+    IVec4(i32,i32,i32,i32),
+    UVec2(u32,u32),
+    UVec3(u32,u32,u32),
+    UVec4(u32,u32,u32,u32),
+    Mat2(math::Mat2, bool),
+    Mat3(math::Mat3, bool),
+    Mat4(math::Mat4, bool),
+    FloatArray(Vec<f32>),
+    IntArray(Vec<i32>),
+    UIntArray(Vec<u32>),
+    BoolArray(Vec<bool>),
+    Vec2Array(Vec<math::Vec2<f32>>),
+    Vec3Array(Vec<math::Vec3<f32>>),
+    Vec4Array(Vec<math::Vec4<f32>>),
+    IVec2Array(Vec<math::Vec2<i32>>),
+    IVec3Array(Vec<math::Vec3<i32>>),
+    IVec4Array(Vec<math::Vec4<i32>>),
+    UVec2Array(Vec<math::Vec2<u32>>),
+    UVec3Array(Vec<math::Vec3<u32>>),
+    UVec4Array(Vec<math::Vec4<u32>>),
+    Mat2Array(Vec<math::Mat2>, bool),
+    Mat3Array(Vec<math::Mat3>, bool),
+    Mat4Array(Vec<math::Mat4>, bool),
+    Sampler3D(GLuint),
+    SamplerCube(GLuint),
+    Sampler2DArray(GLuint),
+    Image2D(GLuint),
+    Image3D(GLuint),
+    ImageCube(GLuint),
+    Image2DArray(GLuint),
+}
+
+impl Uniform {
+    /// Returns true when this value can be assigned to a uniform of the GLSL type.
+    /// Types that glw does not know can not be checked and match every value.
+    pub fn matches(&self, data_type: reflection::DataType) -> bool {
+        use reflection::DataType;
+        match self {
+            _ if matches!(data_type, DataType::Unknown(_)) => true,
+            // Bools can be set with the float, int and uint variants
+            Uniform::Float(_) | Uniform::FloatArray(_) => data_type == DataType::Float || data_type == DataType::Bool,
+            // Texture and image units of samplers and images are set with glUniform1i
+            Uniform::Int(_) | Uniform::IntArray(_) => matches!(
+                data_type,
+                DataType::Int
+                    | DataType::Bool
+                    | DataType::Sampler2D
+                    | DataType::Sampler3D
+                    | DataType::SamplerCube
+                    | DataType::Sampler2DArray
+                    | DataType::Image2D
+                    | DataType::Image3D
+                    | DataType::ImageCube
+                    | DataType::Image2DArray
+            ),
+            Uniform::UInt(_) | Uniform::UIntArray(_) => data_type == DataType::UInt || data_type == DataType::Bool,
+            Uniform::Bool(_) | Uniform::BoolArray(_) => data_type == DataType::Bool,
+            Uniform::Vec2(..) | Uniform::Vec2Array(_) => data_type == DataType::Vec2 || data_type == DataType::BVec2,
+            Uniform::Vec3(..) | Uniform::Vec3Array(_) => data_type == DataType::Vec3 || data_type == DataType::BVec3,
+            Uniform::Vec4(..) | Uniform::Vec4Array(_) => data_type == DataType::Vec4 || data_type == DataType::BVec4,
+            Uniform::IVec2(..) | Uniform::IVec2Array(_) => data_type == DataType::IVec2 || data_type == DataType::BVec2,
+            Uniform::IVec3(..) | Uniform::IVec3Array(_) => data_type == DataType::IVec3 || data_type == DataType::BVec3,
+            Uniform::IVec4(..) | Uniform::IVec4Array(_) => data_type == DataType::IVec4 || data_type == DataType::BVec4,
+            Uniform::UVec2(..) | Uniform::UVec2Array(_) => data_type == DataType::UVec2 || data_type == DataType::BVec2,
+            Uniform::UVec3(..) | Uniform::UVec3Array(_) => data_type == DataType::UVec3 || data_type == DataType::BVec3,
+            Uniform::UVec4(..) | Uniform::UVec4Array(_) => data_type == DataType::UVec4 || data_type == DataType::BVec4,
+            Uniform::Mat2(..) | Uniform::Mat2Array(..) => data_type == DataType::Mat2,
+            Uniform::Mat3(..) | Uniform::Mat3Array(..) => data_type == DataType::Mat3,
+            Uniform::Mat4(..) | Uniform::Mat4Array(..) => data_type == DataType::Mat4,
+            Uniform::Sampler2D(_) => data_type == DataType::Sampler2D,
+            Uniform::Sampler3D(_) => data_type == DataType::Sampler3D,
+            Uniform::SamplerCube(_) => data_type == DataType::SamplerCube,
+            Uniform::Sampler2DArray(_) => data_type == DataType::Sampler2DArray,
+            Uniform::Image2D(_) => data_type == DataType::Image2D,
+            Uniform::Image3D(_) => data_type == DataType::Image3D,
+            Uniform::ImageCube(_) => data_type == DataType::ImageCube,
+            Uniform::Image2DArray(_) => data_type == DataType::Image2DArray,
+        }
+    }
+
+    /// Returns how many array elements the value sets, 1 for values that are not arrays
+    pub fn element_count(&self) -> usize {
+        match self {
+            Uniform::FloatArray(v) => v.len(),
+            Uniform::IntArray(v) => v.len(),
+            Uniform::UIntArray(v) => v.len(),
+            Uniform::BoolArray(v) => v.len(),
+            Uniform::Vec2Array(v) => v.len(),
+            Uniform::Vec3Array(v) => v.len(),
+            Uniform::Vec4Array(v) => v.len(),
+            Uniform::IVec2Array(v) => v.len(),
+            Uniform::IVec3Array(v) => v.len(),
+            Uniform::IVec4Array(v) => v.len(),
+            Uniform::UVec2Array(v) => v.len(),
+            Uniform::UVec3Array(v) => v.len(),
+            Uniform::UVec4Array(v) => v.len(),
+            Uniform::Mat2Array(v, _) => v.len(),
+            Uniform::Mat3Array(v, _) => v.len(),
+            Uniform::Mat4Array(v, _) => v.len(),
+            _ => 1,
+        }
+    }
+}
+
+impl From<f32> for Uniform {
+    fn from(v: f32) -> Uniform {
+        Uniform::Float(v)
+    }
+}
+
+impl From<i32> for Uniform {
+    fn from(v: i32) -> Uniform {
+        Uniform::Int(v)
+    }
+}
+
+impl From<u32> for Uniform {
+    fn from(v: u32) -> Uniform {
+        Uniform::UInt(v)
+    }
+}
+
+impl From<bool> for Uniform {
+    fn from(v: bool) -> Uniform {
+        Uniform::Bool(v)
+    }
+}
+
+impl From<math::Vec2<f32>> for Uniform {
+    fn from(v: math::Vec2<f32>) -> Uniform {
+        Uniform::Vec2(v.x, v.y)
+    }
+}
+
+impl From<math::Vec3<f32>> for Uniform {
+    fn from(v: math::Vec3<f32>) -> Uniform {
+        Uniform::Vec3(v.x, v.y, v.z)
+    }
+}
+
+impl From<math::Vec4<f32>> for Uniform {
+    fn from(v: math::Vec4<f32>) -> Uniform {
+        Uniform::Vec4(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<math::Vec2<i32>> for Uniform {
+    fn from(v: math::Vec2<i32>) -> Uniform {
+        Uniform::IVec2(v.x, v.y)
+    }
+}
+
+impl From<math::Vec3<i32>> for Uniform {
+    fn from(v: math::Vec3<i32>) -> Uniform {
+        Uniform::IVec3(v.x, v.y, v.z)
+    }
+}
+
+impl From<math::Vec4<i32>> for Uniform {
+    fn from(v: math::Vec4<i32>) -> Uniform {
+        Uniform::IVec4(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<math::Vec2<u32>> for Uniform {
+    fn from(v: math::Vec2<u32>) -> Uniform {
+        Uniform::UVec2(v.x, v.y)
+    }
+}
+
+impl From<math::Vec3<u32>> for Uniform {
+    fn from(v: math::Vec3<u32>) -> Uniform {
+        Uniform::UVec3(v.x, v.y, v.z)
+    }
+}
+
+impl From<math::Vec4<u32>> for Uniform {
+    fn from(v: math::Vec4<u32>) -> Uniform {
+        Uniform::UVec4(v.x, v.y, v.z, v.w)
+    }
+}
+
+impl From<math::Mat2> for Uniform {
+    fn from(v: math::Mat2) -> Uniform {
+        Uniform::Mat2(v, false)
+    }
+}
+
+impl From<math::Mat3> for Uniform {
+    fn from(v: math::Mat3) -> Uniform {
+        Uniform::Mat3(v, false)
+    }
+}
+
+impl From<math::Mat4> for Uniform {
+    fn from(v: math::Mat4) -> Uniform {
+        Uniform::Mat4(v, false)
+    }
+}
+
+#[cfg(test)]
+mod uniform_tests {
+    use super::*;
+    use reflection::DataType;
+
+    #[test]
+    fn converts_math_types() {
+        assert_eq!(Uniform::from(math::Vec3::new(1.0, 2.0, 3.0)), Uniform::Vec3(1.0, 2.0, 3.0));
+        assert_eq!(Uniform::from(math::Vec2::new(1u32, 2u32)), Uniform::UVec2(1, 2));
+        assert_eq!(Uniform::from(math::Mat4::identity()), Uniform::Mat4(math::Mat4::identity(), false));
+    }
+
+    #[test]
+    fn arrays_match_their_element_type() {
+        assert!(Uniform::Vec4Array(vec![math::Vec4::default()]).matches(DataType::Vec4));
+        assert!(Uniform::Mat3Array(vec![], true).matches(DataType::Mat3));
+        assert!(!Uniform::FloatArray(vec![1.0]).matches(DataType::Vec2));
+        assert!(Uniform::Bool(true).matches(DataType::Bool));
+        assert_eq!(Uniform::Vec2Array(vec![math::Vec2::default(); 3]).element_count(), 3);
+        assert_eq!(Uniform::Float(1.0).element_count(), 1);
+    }
+
+    #[test]
+    fn bools_accept_float_int_and_uint_values() {
+        assert!(Uniform::Float(1.0).matches(DataType::Bool));
+        assert!(Uniform::UInt(1).matches(DataType::Bool));
+        assert!(Uniform::Vec3(1.0, 0.0, 1.0).matches(DataType::BVec3));
+        assert!(Uniform::UVec2(1, 0).matches(DataType::BVec2));
+        assert!(!Uniform::Vec3(1.0, 0.0, 1.0).matches(DataType::BVec4));
+    }
+
+    #[test]
+    fn ints_set_texture_units() {
+        assert!(Uniform::Int(0).matches(DataType::Sampler2D));
+        assert!(Uniform::IntArray(vec![0, 1]).matches(DataType::Image2DArray));
+        assert!(!Uniform::Float(0.0).matches(DataType::Sampler2D));
+    }
+
+    #[test]
+    fn unknown_types_match_every_value() {
+        assert!(Uniform::Int(0).matches(DataType::Unknown(gl::SAMPLER_2D_SHADOW)));
+        assert!(Uniform::Mat4(math::Mat4::identity(), false).matches(DataType::Unknown(gl::FLOAT_MAT4x3)));
+    }
 }
@@ -0,0 +1,204 @@
+use super::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Not part of the GL 4.5 bindings generated by the gl crate
+const SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+type SpecializeShaderFn = extern "system" fn(GLuint, *const GLchar, GLuint, *const GLuint, *const GLuint);
+
+// Address of glSpecializeShader (GL 4.6) or glSpecializeShaderARB, 0 when the driver does not provide it
+static SPECIALIZE_SHADER: AtomicUsize = AtomicUsize::new(0);
+
+/// Loads the SPIR-V entry points that are not part of the gl crate, called when a context is created
+pub(crate) fn load_with<F: FnMut(&'static str) -> *const c_void>(mut loader: F) {
+    let mut address = loader("glSpecializeShader");
+    if address.is_null() {
+        address = loader("glSpecializeShaderARB");
+    }
+    SPECIALIZE_SHADER.store(address as usize, Ordering::SeqCst);
+}
+
+/// Returns true when the current context can load SPIR-V shaders (GL 4.6 or ARB_gl_spirv)
+pub fn is_supported() -> bool {
+    if SPECIALIZE_SHADER.load(Ordering::SeqCst) == 0 {
+        return false;
+    }
+
+    unsafe {
+        let mut count: GLint = 0;
+        gl::GetIntegerv(gl::NUM_SHADER_BINARY_FORMATS, &mut count);
+
+        let mut formats: Vec<GLint> = vec![0; count.max(0) as usize];
+        if !formats.is_empty() {
+            gl::GetIntegerv(gl::SHADER_BINARY_FORMATS, formats.as_mut_ptr());
+        }
+        formats.contains(&(SHADER_BINARY_FORMAT_SPIR_V as GLint))
+    }
+}
+
+/// Checks that ```binary``` looks like a SPIR-V module: a whole number of words starting with the SPIR-V magic number
+pub(crate) fn validate(binary: &[u8]) -> Result<(), Error> {
+    if binary.len() < 20 || binary.len() % 4 != 0 {
+        return Err(Error::InvalidArgument(format!("a SPIR-V module is a sequence of 32-bit words, got {} bytes", binary.len())));
+    }
+
+    let magic = [binary[0], binary[1], binary[2], binary[3]];
+    if u32::from_le_bytes(magic) != SPIRV_MAGIC && u32::from_be_bytes(magic) != SPIRV_MAGIC {
+        return Err(Error::InvalidArgument("the binary does not start with the SPIR-V magic number".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Uploads the SPIR-V binary to the shader object and specializes it.
+/// The caller checks the compile status.
+pub(crate) fn specialize(shader: GLuint, binary: &[u8], specialization: &Specialization) -> Result<(), Error> {
+    validate(binary)?;
+
+    if !is_supported() {
+        return Err(Error::Unsupported("SPIR-V shaders require OpenGL 4.6 or ARB_gl_spirv".to_string()));
+    }
+
+    let entry_point = std::ffi::CString::new(specialization.entry_point.as_str())?;
+    let indices: Vec<GLuint> = specialization.constants.iter().map(|(id, _)| *id).collect();
+    let values: Vec<GLuint> = specialization.constants.iter().map(|(_, value)| value.0).collect();
+
+    unsafe {
+        gl::ShaderBinary(1, &shader, SHADER_BINARY_FORMAT_SPIR_V, binary.as_ptr() as *const c_void, binary.len() as GLsizei);
+
+        let specialize: SpecializeShaderFn = std::mem::transmute(SPECIALIZE_SHADER.load(Ordering::SeqCst));
+        specialize(shader, entry_point.as_ptr(), indices.len() as GLuint, indices.as_ptr(), values.as_ptr());
+    }
+
+    Ok(())
+}
+
+/// Hashes the binary together with the entry point and constants it is specialized with
+pub(crate) fn hash(binary: &[u8], specialization: &Specialization) -> u64 {
+    let mut hash = programcache::fnv1a(programcache::hash(binary), specialization.entry_point.as_bytes());
+    for (id, value) in &specialization.constants {
+        hash = programcache::fnv1a(hash, &id.to_le_bytes());
+        hash = programcache::fnv1a(hash, &value.0.to_le_bytes());
+    }
+    hash
+}
+
+/// Raw 32-bit value of a specialization constant
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SpecializationValue(pub u32);
+
+impl From<u32> for SpecializationValue {
+    fn from(value: u32) -> Self {
+        SpecializationValue(value)
+    }
+}
+
+impl From<i32> for SpecializationValue {
+    fn from(value: i32) -> Self {
+        SpecializationValue(value as u32)
+    }
+}
+
+impl From<f32> for SpecializationValue {
+    fn from(value: f32) -> Self {
+        SpecializationValue(value.to_bits())
+    }
+}
+
+impl From<bool> for SpecializationValue {
+    fn from(value: bool) -> Self {
+        SpecializationValue(value as u32)
+    }
+}
+
+/// # Specialization
+/// Entry point and specialization constants used when loading a SPIR-V shader.
+/// Constants are identified by their ```constant_id``` in the shader, constants that are not set keep their default value.
+/// ```
+/// use glw::spirv::Specialization;
+///
+/// let mut specialization = Specialization::new();
+/// specialization
+///     .with_entry_point("main")
+///     .with_constant(0, 16u32)
+///     .with_constant(1, 0.5f32);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Specialization {
+    entry_point: String,
+    constants: Vec<(u32, SpecializationValue)>,
+}
+
+impl Default for Specialization {
+    fn default() -> Self {
+        Specialization {
+            entry_point: "main".to_string(),
+            constants: Vec::new(),
+        }
+    }
+}
+
+impl Specialization {
+    pub fn new() -> Specialization {
+        Specialization::default()
+    }
+
+    /// Sets the function used as entry point, defaults to "main"
+    pub fn with_entry_point(&mut self, name: &str) -> &mut Self {
+        self.entry_point = name.to_string();
+
+        self
+    }
+
+    pub fn with_constant<T: Into<SpecializationValue>>(&mut self, id: u32, value: T) -> &mut Self {
+        let value = value.into();
+        match self.constants.iter_mut().find(|(existing, _)| *existing == id) {
+            Some(constant) => constant.1 = value,
+            None => self.constants.push((id, value)),
+        }
+
+        self
+    }
+
+    pub fn get_entry_point(&self) -> &str {
+        &self.entry_point
+    }
+
+    pub fn get_constants(&self) -> &[(u32, SpecializationValue)] {
+        &self.constants
+    }
+}
+
+#[cfg(test)]
+mod spirv_tests {
+    use super::*;
+
+    #[test]
+    fn validates_binary() {
+        let mut module = SPIRV_MAGIC.to_le_bytes().to_vec();
+        module.extend_from_slice(&[0; 16]);
+        assert!(validate(&module).is_ok());
+
+        assert!(matches!(validate(&module[..18]), Err(Error::InvalidArgument(_))));
+        assert!(matches!(validate(&[0; 20]), Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn constants_are_stored_as_bits() {
+        let mut specialization = Specialization::new();
+        specialization
+            .with_constant(0, 16u32)
+            .with_constant(1, 1.0f32)
+            .with_constant(2, true)
+            .with_constant(0, -1);
+
+        assert_eq!(specialization.get_entry_point(), "main");
+        assert_eq!(specialization.get_constants(), &[
+            (0, SpecializationValue(u32::MAX)),
+            (1, SpecializationValue(0x3f80_0000)),
+            (2, SpecializationValue(1)),
+        ]);
+    }
+}
@@ -0,0 +1,242 @@
+use super::*;
+
+use super::reflection::{BlockInfo, BlockMember, DataType, UniformInfo, VertexInput};
+use naga::front::glsl;
+
+/// Parses and validates GLSL without an OpenGL context and reflects the resources it declares.
+/// Errors are returned as ```Error::ShaderCompile``` with the same diagnostics as runtime compilation.
+/// Only the subset of GLSL understood by naga is supported, e.g. uniforms need to be declared in blocks
+/// and combined samplers (```sampler2D```) are not accepted.
+/// Locations of opaque uniforms are assigned at link time and reported as -1.
+pub fn validate_glsl(source: &str, stage: ShaderType) -> Result<ProgramReflection, Error> {
+    let naga_stage = match stage {
+        ShaderType::Vertex => naga::ShaderStage::Vertex,
+        ShaderType::Fragment => naga::ShaderStage::Fragment,
+        ShaderType::Compute => naga::ShaderStage::Compute,
+        _ => return Err(Error::Unsupported(format!("offline validation of {:?} shaders", stage))),
+    };
+
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(naga_stage), source)
+        .map_err(|errors| Error::ShaderCompile {
+            stage,
+            log: errors.emit_to_string(source),
+            diagnostics: errors.errors.iter().map(|e| diagnostic(e.kind.to_string(), e.location(source))).collect(),
+        })?;
+
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|err| Error::ShaderCompile {
+            stage,
+            log: err.emit_to_string(source),
+            diagnostics: vec![diagnostic(err.as_inner().to_string(), err.location(source))],
+        })?;
+
+    Ok(reflect(&module))
+}
+
+/// Loads a file through the preprocessor and validates it, diagnostics refer to the original files
+pub fn validate_file(path: &str, stage: ShaderType, preprocessor: &Preprocessor) -> Result<ProgramReflection, Error> {
+    let source = preprocessor.process_file(path)?;
+
+    validate_glsl(&source.source, stage).map_err(|err| match err {
+        Error::ShaderCompile { stage, log, diagnostics } => Error::ShaderCompile {
+            stage,
+            log: source.map_log(&log),
+            diagnostics: diagnostics.iter().map(|d| source.map_diagnostic(d)).collect(),
+        },
+        err => err,
+    })
+}
+
+fn diagnostic(message: String, location: Option<naga::SourceLocation>) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        file: 0,
+        line: location.map(|l| l.line_number).unwrap_or(0),
+        column: location.map(|l| l.line_position),
+        message,
+    }
+}
+
+fn reflect(module: &naga::Module) -> ProgramReflection {
+    let mut reflection = ProgramReflection::default();
+
+    for (_, global) in module.global_variables.iter() {
+        let ty = &module.types[global.ty];
+        let binding = global.binding.as_ref().map(|b| b.binding).unwrap_or(0);
+        let name = global.name.clone().or_else(|| ty.name.clone()).unwrap_or_default();
+
+        match global.space {
+            naga::AddressSpace::Uniform => reflection.uniform_blocks.push(block(module, ty, name, binding)),
+            naga::AddressSpace::Storage { .. } => reflection.storage_blocks.push(block(module, ty, name, binding)),
+            naga::AddressSpace::Handle => reflection.uniforms.push(UniformInfo {
+                name,
+                data_type: data_type(&ty.inner),
+                location: -1,
+                array_size: 1,
+            }),
+            _ => (),
+        }
+    }
+
+    for entry_point in &module.entry_points {
+        for argument in &entry_point.function.arguments {
+            if let (naga::ShaderStage::Vertex, Some(naga::Binding::Location { location, .. })) = (entry_point.stage, &argument.binding) {
+                reflection.inputs.push(VertexInput {
+                    name: argument.name.clone().unwrap_or_default(),
+                    data_type: data_type(&module.types[argument.ty].inner),
+                    location: *location as i32,
+                    array_size: 1,
+                });
+            }
+        }
+    }
+
+    reflection
+}
+
+fn block(module: &naga::Module, ty: &naga::Type, name: String, binding: u32) -> BlockInfo {
+    let (members, span) = match ty.inner {
+        naga::TypeInner::Struct { ref members, span } => (members.as_slice(), span),
+        ref inner => (&[][..], inner.size(module.to_ctx())),
+    };
+
+    BlockInfo {
+        name,
+        binding,
+        data_size: span as i32,
+        members: members.iter().map(|member| {
+            let inner = &module.types[member.ty].inner;
+            let (element, array_size, array_stride) = match *inner {
+                naga::TypeInner::Array { base, size, stride } => {
+                    let size = match size {
+                        naga::ArraySize::Constant(size) => size.get() as i32,
+                        _ => 0,
+                    };
+                    (&module.types[base].inner, size, stride as i32)
+                }
+                _ => (inner, 1, 0),
+            };
+            let matrix_stride = match *element {
+                naga::TypeInner::Matrix { columns, .. } => (element.size(module.to_ctx()) / columns as u32) as i32,
+                _ => 0,
+            };
+
+            BlockMember {
+                name: member.name.clone().unwrap_or_default(),
+                data_type: data_type(element),
+                offset: member.offset as i32,
+                array_size,
+                array_stride,
+                matrix_stride,
+            }
+        }).collect(),
+    }
+}
+
+fn data_type(inner: &naga::TypeInner) -> DataType {
+    use naga::{ImageDimension, ScalarKind, TypeInner, VectorSize};
+
+    match *inner {
+        TypeInner::Scalar(scalar) => match scalar.kind {
+            ScalarKind::Float => DataType::Float,
+            ScalarKind::Sint => DataType::Int,
+            ScalarKind::Uint => DataType::UInt,
+            ScalarKind::Bool => DataType::Bool,
+            _ => DataType::Unknown(0),
+        },
+        TypeInner::Vector { size, scalar } => match (scalar.kind, size) {
+            (ScalarKind::Float, VectorSize::Bi) => DataType::Vec2,
+            (ScalarKind::Float, VectorSize::Tri) => DataType::Vec3,
+            (ScalarKind::Float, VectorSize::Quad) => DataType::Vec4,
+            (ScalarKind::Sint, VectorSize::Bi) => DataType::IVec2,
+            (ScalarKind::Sint, VectorSize::Tri) => DataType::IVec3,
+            (ScalarKind::Sint, VectorSize::Quad) => DataType::IVec4,
+            (ScalarKind::Uint, VectorSize::Bi) => DataType::UVec2,
+            (ScalarKind::Uint, VectorSize::Tri) => DataType::UVec3,
+            (ScalarKind::Uint, VectorSize::Quad) => DataType::UVec4,
+            (ScalarKind::Bool, VectorSize::Bi) => DataType::BVec2,
+            (ScalarKind::Bool, VectorSize::Tri) => DataType::BVec3,
+            (ScalarKind::Bool, VectorSize::Quad) => DataType::BVec4,
+            _ => DataType::Unknown(0),
+        },
+        TypeInner::Matrix { columns, rows, .. } => match (columns, rows) {
+            (VectorSize::Bi, VectorSize::Bi) => DataType::Mat2,
+            (VectorSize::Tri, VectorSize::Tri) => DataType::Mat3,
+            (VectorSize::Quad, VectorSize::Quad) => DataType::Mat4,
+            _ => DataType::Unknown(0),
+        },
+        TypeInner::Image { dim, arrayed, class: naga::ImageClass::Storage { .. } } => match (dim, arrayed) {
+            (ImageDimension::D2, false) => DataType::Image2D,
+            (ImageDimension::D2, true) => DataType::Image2DArray,
+            (ImageDimension::D3, _) => DataType::Image3D,
+            (ImageDimension::Cube, _) => DataType::ImageCube,
+            _ => DataType::Unknown(0),
+        },
+        _ => DataType::Unknown(0),
+    }
+}
+
+#[cfg(test)]
+mod validation_tests {
+    use super::*;
+
+    #[test]
+    fn reflects_blocks_and_inputs() {
+        let source = "#version 450
+layout(std430, binding = 1) buffer Cells { uint width; vec3 pad; uint cells[]; };
+layout(std140, binding = 0) uniform Params { float dt; mat4 mvp; } params;
+layout(location = 0) in vec3 position;
+void main() { cells[0] = uint(params.dt) + width; gl_Position = params.mvp * vec4(position, 1.0); }
+";
+        let reflection = validate_glsl(source, ShaderType::Vertex).unwrap();
+
+        let cells = reflection.get_storage_block_at(1).unwrap();
+        assert_eq!(cells.members[1].offset, 16);
+        assert_eq!((cells.members[2].offset, cells.members[2].array_size, cells.members[2].array_stride), (28, 0, 4));
+        assert!(cells.check_element_size::<u32>().is_ok());
+        assert!(cells.check_element_size::<u64>().is_err());
+
+        let params = &reflection.uniform_blocks[0];
+        assert_eq!((params.binding, params.data_size), (0, 80));
+        assert_eq!((params.members[1].data_type, params.members[1].matrix_stride), (DataType::Mat4, 16));
+
+        assert_eq!(reflection.inputs, vec![VertexInput { name: "position".to_string(), data_type: DataType::Vec3, location: 0, array_size: 1 }]);
+    }
+
+    #[test]
+    fn reports_diagnostics() {
+        let result = validate_glsl("#version 450\nvoid main() {\n    oops;\n}\n", ShaderType::Fragment);
+
+        match result {
+            Err(Error::ShaderCompile { diagnostics, .. }) => {
+                assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, Some(5)));
+            }
+            _ => panic!("expected a compile error"),
+        }
+    }
+
+    #[derive(crate::layout::GlslStruct)]
+    struct Material {
+        albedo: crate::Vec3<f32>,
+        roughness: f32,
+        weights: [f32; 3],
+        transform: crate::Mat3,
+    }
+
+    #[test]
+    fn glsl_structs_match_the_block_layouts() {
+        use crate::layout::GlslStruct;
+
+        let source = format!("#version 450
+{}layout(std140, binding = 0) uniform Materials {{ Material material; }};
+layout(std430, binding = 1) buffer Instances {{ Material instances[]; }};
+void main() {{ instances[0].roughness = material.roughness; }}
+", Material::glsl_definition());
+        let reflection = validate_glsl(&source, ShaderType::Compute).unwrap();
+
+        assert_eq!(reflection.uniform_blocks[0].data_size as usize, std::mem::size_of::<MaterialStd140>());
+        assert!(reflection.get_storage_block_at(1).unwrap().check_element_size::<MaterialStd430>().is_ok());
+    }
+}
@@ -0,0 +1,225 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// # Variant Key
+/// The set of feature flags enabled in a shader variant. Every flag is injected as ```#define <FLAG> 1```.
+/// The order in which flags are given does not matter.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct VariantKey {
+    features: Vec<String>,
+}
+
+impl VariantKey {
+    pub fn new(features: &[&str]) -> VariantKey {
+        let mut features: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+        features.sort();
+        features.dedup();
+
+        VariantKey { features }
+    }
+
+    pub fn get_features(&self) -> &[String] {
+        &self.features
+    }
+
+    pub fn contains(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+impl From<&[&str]> for VariantKey {
+    fn from(features: &[&str]) -> Self {
+        VariantKey::new(features)
+    }
+}
+
+struct VariantStage {
+    stage: ShaderType,
+    path: PathBuf,
+    // Features referenced by the source of this stage, found on first use
+    features: Option<Vec<String>>,
+    shaders: HashMap<VariantKey, Rc<Shader>>,
+}
+
+impl VariantStage {
+    /// Finds which of the declared features are referenced by the source of this stage
+    fn scan(&mut self, declared: &[String], preprocessor: &Preprocessor) -> Result<(), Error> {
+        if self.features.is_none() {
+            let source = preprocessor.process_file(&self.path)?;
+            // Only whole identifiers count, so SHADOW is not referenced by a source that uses SHADOWS_PCF
+            let identifiers: Vec<&str> = source.source.split(|c: char| !(c.is_alphanumeric() || c == '_')).collect();
+            self.features = Some(declared.iter().filter(|f| identifiers.contains(&f.as_str())).cloned().collect());
+        }
+
+        Ok(())
+    }
+
+    /// Reduces ```key``` to the features this stage references, so variants that only differ in
+    /// features used by other stages share the shader
+    fn stage_key(&self, key: &VariantKey) -> VariantKey {
+        let referenced = self.features.as_deref().unwrap_or(&[]);
+        VariantKey {
+            features: key.features.iter().filter(|f| referenced.contains(f)).cloned().collect(),
+        }
+    }
+
+    fn get_shader(&mut self, key: &VariantKey, declared: &[String], preprocessor: &Preprocessor) -> Result<Rc<Shader>, Error> {
+        self.scan(declared, preprocessor)?;
+
+        let key = self.stage_key(key);
+        if let Some(shader) = self.shaders.get(&key) {
+            return Ok(shader.clone());
+        }
+
+        let mut defines = preprocessor.clone();
+        for feature in &key.features {
+            defines.with_define(feature, "1");
+        }
+
+        let mut shader = Shader::new(self.stage);
+        shader.load_from_file_with(&self.path.to_string_lossy(), &defines)?;
+
+        let shader = Rc::new(shader);
+        self.shaders.insert(key, shader.clone());
+        Ok(shader)
+    }
+}
+
+struct VariantState {
+    features: Vec<String>,
+    stages: Vec<VariantStage>,
+    pipelines: HashMap<VariantKey, Rc<GraphicsPipeline>>,
+}
+
+/// # Shader Variant Set
+/// Builds graphics pipelines from the same vertex and fragment shader files with different combinations of feature flags.
+/// Variants are compiled the first time they are requested and cached afterwards.
+/// ```
+/// use glw::variants::{ShaderVariantSet, VariantKey};
+///
+/// let mut variants = ShaderVariantSet::new("shaders/mesh.vert", "shaders/mesh.frag");
+/// variants.with_feature("TEXTURED").with_feature("LIGHTING_PBR");
+///
+/// let key = VariantKey::new(&["TEXTURED"]);
+/// ```
+/// The pipeline for a key is retrieved with ```get``` or bound directly with ```CommandList::bind_variant```.
+pub struct ShaderVariantSet {
+    preprocessor: Preprocessor,
+    state: RefCell<VariantState>,
+}
+
+impl ShaderVariantSet {
+    pub fn new<P: AsRef<Path>>(vertex: P, fragment: P) -> ShaderVariantSet {
+        let stage = |stage, path: P| VariantStage {
+            stage,
+            path: path.as_ref().to_path_buf(),
+            features: None,
+            shaders: HashMap::new(),
+        };
+
+        ShaderVariantSet {
+            preprocessor: Preprocessor::default(),
+            state: RefCell::new(VariantState {
+                features: Vec::new(),
+                stages: vec![stage(ShaderType::Vertex, vertex), stage(ShaderType::Fragment, fragment)],
+                pipelines: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Sets the preprocessor used for all variants, e.g. to add search paths or defines shared by every variant
+    pub fn with_preprocessor(&mut self, preprocessor: Preprocessor) -> &mut Self {
+        self.preprocessor = preprocessor;
+        self.clear();
+
+        self
+    }
+
+    /// Declares a feature flag that can be used in variant keys
+    pub fn with_feature(&mut self, name: &str) -> &mut Self {
+        let state = self.state.get_mut();
+        if !state.features.iter().any(|f| f == name) {
+            state.features.push(name.to_string());
+        }
+        for stage in &mut state.stages {
+            stage.features = None;
+        }
+
+        self
+    }
+
+    /// Returns the pipeline of a variant, compiling and linking it on first use.
+    /// Keys containing features that were not declared with ```with_feature``` are rejected.
+    pub fn get(&self, key: &VariantKey) -> Result<Rc<GraphicsPipeline>, Error> {
+        let mut state = self.state.borrow_mut();
+        if let Some(pipeline) = state.pipelines.get(key) {
+            return Ok(pipeline.clone());
+        }
+
+        if let Some(unknown) = key.features.iter().find(|f| !state.features.contains(f)) {
+            return Err(Error::InvalidArgument(format!("\"{}\" is not a feature of this variant set", unknown)));
+        }
+
+        let declared = state.features.clone();
+        let vertex = state.stages[0].get_shader(key, &declared, &self.preprocessor)?;
+        let fragment = state.stages[1].get_shader(key, &declared, &self.preprocessor)?;
+
+        let pipeline = Rc::new(PipelineBuilder::new()
+            .with_vertex_shader(vertex)
+            .with_fragment_shader(fragment)
+            .build()?);
+
+        state.pipelines.insert(key.clone(), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Returns the number of variants that have been built
+    pub fn get_variant_count(&self) -> usize {
+        self.state.borrow().pipelines.len()
+    }
+
+    /// Drops all cached shaders and pipelines, they are rebuilt on their next use
+    pub fn clear(&mut self) {
+        let state = self.state.get_mut();
+        state.pipelines.clear();
+        for stage in &mut state.stages {
+            stage.features = None;
+            stage.shaders.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod variant_tests {
+    use super::*;
+
+    #[test]
+    fn keys_ignore_order_and_duplicates() {
+        assert_eq!(VariantKey::new(&["B", "A", "B"]), VariantKey::new(&["A", "B"]));
+        assert!(VariantKey::new(&["A"]).contains("A"));
+        assert!(!VariantKey::new(&["A"]).contains("B"));
+    }
+
+    #[test]
+    fn stage_keys_only_contain_referenced_features() {
+        let dir = std::env::temp_dir().join(format!("glw_{}", std::process::id())).join("variants");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("mesh.vert"), "#ifdef SKINNED\n#endif\n#if defined(SHADOWS_PCF)\n#endif\nvoid main() {}\n").unwrap();
+
+        let mut stage = VariantStage {
+            stage: ShaderType::Vertex,
+            path: dir.join("mesh.vert"),
+            features: None,
+            shaders: HashMap::new(),
+        };
+
+        stage.scan(&["SKINNED".to_string(), "TEXTURED".to_string(), "SHADOW".to_string()], &Preprocessor::default()).unwrap();
+
+        assert_eq!(stage.stage_key(&VariantKey::new(&["TEXTURED", "SKINNED", "SHADOW"])), VariantKey::new(&["SKINNED"]));
+        assert_eq!(stage.stage_key(&VariantKey::new(&["TEXTURED"])), VariantKey::default());
+    }
+}